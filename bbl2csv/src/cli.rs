@@ -38,6 +38,7 @@ pub(crate) fn print_help(bin: &str) {
   -f, --filter <fields>           Select fields to output by name, excluding any suffixed index
                                   (comma separated)
   -F, --gps-filter <fields>       Same as --filter, but for GPS fields. Implies --gps
+      --lenient                   Decode logs from unknown firmware as if from Betaflight
   -v, --verbose                   Increase debug output up to {max_verbose} times
   -q, --quiet                     Reduce debug output up to {max_quiet} times
   -h, --help                      Print this help
@@ -65,6 +66,7 @@ pub(crate) struct Cli {
     pub gps: bool,
     pub filter: Option<Vec<String>>,
     pub gps_filter: Option<Vec<String>>,
+    pub lenient: bool,
     pub verbosity: LevelFilter,
     pub logs: Vec<PathBuf>,
 }
@@ -88,6 +90,7 @@ fn parse_filter(parser: &mut lexopt::Parser) -> Result<Vec<String>, lexopt::Erro
         let mut gps = false;
         let mut filter = None;
         let mut gps_filter = None;
+        let mut lenient = false;
         let mut verbosity = DEFAULT_VERBOSITY;
         let mut logs = Vec::new();
 
@@ -104,6 +107,7 @@ fn parse_filter(parser: &mut lexopt::Parser) -> Result<Vec<String>, lexopt::Erro
                     gps = true;
                     gps_filter = Some(parse_filter(&mut parser)?);
                 }
+                Long("lenient") => lenient = true,
                 Short('v') | Long("verbose") => verbosity += 1,
                 Short('q') | Long("quiet") => verbosity -= 1,
                 Short('h') | Long("help") => return Ok(Action::Help),
@@ -121,6 +125,7 @@ fn parse_filter(parser: &mut lexopt::Parser) -> Result<Vec<String>, lexopt::Erro
             gps,
             filter,
             gps_filter,
+            lenient,
             verbosity: verbosity_from_int(verbosity),
             logs,
         }))
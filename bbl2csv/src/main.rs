@@ -73,7 +73,13 @@ fn main() {
             let mut log = file.get_reader(i);
 
             let headers: Headers = {
-                let mut headers = Headers::parse(&mut log).map_err(|err| {
+                let headers = if cli.lenient {
+                    Headers::parse_lenient(&mut log)
+                } else {
+                    Headers::parse(&mut log)
+                };
+
+                let mut headers = headers.map_err(|err| {
                     tracing::debug!("header parse error: {err}");
                     exitcode::DATAERR
                 })?;
@@ -229,6 +235,8 @@ fn format_float(f: f64) -> String {
             format_float(a.get::<si::acceleration::meter_per_second_squared>())
         }
         Value::Rotation(r) => format_float(r.get::<si::angular_velocity::degree_per_second>()),
+        Value::Frequency(f) => format_float(f.get::<si::frequency::hertz>()),
+        Value::Percent(p) => format_float(p.get::<si::ratio::percent>()),
         Value::FlightMode(f) => f.to_string(),
         Value::State(s) => s.to_string(),
         Value::FailsafePhase(f) => f.to_string(),
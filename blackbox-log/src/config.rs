@@ -0,0 +1,598 @@
+//! Typed flight controller configuration from the headers. See
+//! [`Headers::config`](crate::Headers::config).
+//!
+//! Every value is optional, since which headers are written depends on the
+//! firmware and its version.
+
+use alloc::vec::Vec;
+use core::str::FromStr;
+
+use hashbrown::HashMap;
+
+use crate::headers::FirmwareKind;
+
+/// All headers parsed into a [`Config`], including older names and INAV
+/// variants.
+const HEADERS: &[&str] = &[
+    "rollPID",
+    "pitchPID",
+    "yawPID",
+    "d_min",
+    "feedforward_weight",
+    "ff_weight",
+    "rates_type",
+    "rc_rates",
+    "rc_expo",
+    "rc_yaw_expo",
+    "rates",
+    "rate_limits",
+    "gyro_lowpass_type",
+    "gyro_lowpass_hz",
+    "gyro_lowpass_dyn_hz",
+    "gyro_lowpass2_type",
+    "gyro_lowpass2_hz",
+    "gyro_lpf1_type",
+    "gyro_lpf1_static_hz",
+    "gyro_lpf1_dyn_hz",
+    "gyro_lpf2_type",
+    "gyro_lpf2_static_hz",
+    "gyro_lpf_type",
+    "gyro_lpf_hz",
+    "dterm_filter_type",
+    "dterm_lowpass_hz",
+    "dterm_lowpass_dyn_hz",
+    "dterm_filter2_type",
+    "dterm_lowpass2_hz",
+    "dterm_lpf1_type",
+    "dterm_lpf1_static_hz",
+    "dterm_lpf1_dyn_hz",
+    "dterm_lpf2_type",
+    "dterm_lpf2_static_hz",
+    "dterm_lpf_type",
+    "dterm_lpf_hz",
+    "dterm_lpf2_hz",
+    "gyro_notch_hz",
+    "gyro_notch_cutoff",
+    "dterm_notch_hz",
+    "dterm_notch_cutoff",
+    "dyn_notch_count",
+    "dyn_notch_q",
+    "dyn_notch_min_hz",
+    "dyn_notch_max_hz",
+    "dyn_notch_width_percent",
+    "gyro_rpm_notch_harmonics",
+    "gyro_rpm_notch_q",
+    "gyro_rpm_notch_min",
+    "dterm_rpm_notch_harmonics",
+    "dterm_rpm_notch_q",
+    "dterm_rpm_notch_min",
+    "rpm_notch_lpf",
+    "rpm_filter_harmonics",
+    "rpm_filter_q",
+    "rpm_filter_min_hz",
+    "rpm_filter_lpf_hz",
+    "anti_gravity_mode",
+    "anti_gravity_threshold",
+    "anti_gravity_gain",
+    "anti_gravity_cutoff_hz",
+    "tpa_rate",
+    "tpa_breakpoint",
+    "looptime",
+    "pid_process_denom",
+];
+
+/// Tuning and filter configuration read from the headers.
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[non_exhaustive]
+pub struct Config {
+    pub pid: Axes<Pid>,
+    pub rates: Rates,
+    pub filters: Filters,
+    pub anti_gravity: AntiGravity,
+    pub tpa: Tpa,
+    /// Gyro loop time in microseconds. (`looptime` header)
+    pub looptime: Option<u16>,
+    /// The number of gyro loops per PID loop. (`pid_process_denom` header)
+    pub pid_process_denom: Option<u8>,
+}
+
+/// One value for each of roll, pitch & yaw.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Axes<T> {
+    pub roll: T,
+    pub pitch: T,
+    pub yaw: T,
+}
+
+impl<T: Copy> Axes<T> {
+    /// Returns the value for axis `0`, `1` or `2`, in the order roll, pitch,
+    /// yaw.
+    pub(crate) const fn get(&self, axis: usize) -> T {
+        match axis {
+            0 => self.roll,
+            1 => self.pitch,
+            _ => self.yaw,
+        }
+    }
+
+    fn from_slice(values: &[T]) -> Option<Self> {
+        match *values {
+            [roll, pitch, yaw, ..] => Some(Self { roll, pitch, yaw }),
+            _ => None,
+        }
+    }
+}
+
+/// PID gains for a single axis.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[non_exhaustive]
+pub struct Pid {
+    pub p: Option<u16>,
+    pub i: Option<u16>,
+    pub d: Option<u16>,
+    /// The minimum D gain when D max is enabled.
+    pub d_min: Option<u16>,
+    pub feedforward: Option<u16>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[non_exhaustive]
+pub struct Rates {
+    /// How the rates below are interpreted. This is always `None` for INAV.
+    pub kind: Option<RatesType>,
+    pub rc_rates: Option<Axes<u8>>,
+    pub rc_expo: Option<Axes<u8>>,
+    pub rates: Option<Axes<u8>>,
+    /// Maximum rotation rate in degrees / second.
+    pub rate_limits: Option<Axes<u16>>,
+}
+
+/// The type of rates. (`rates_type` header)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum RatesType {
+    Betaflight,
+    Raceflight,
+    Kiss,
+    Actual,
+    Quick,
+    Unknown(u8),
+}
+
+impl RatesType {
+    fn new(raw: u8, firmware: FirmwareKind) -> Option<Self> {
+        if firmware == FirmwareKind::Inav {
+            return None;
+        }
+
+        Some(match raw {
+            0 => Self::Betaflight,
+            1 => Self::Raceflight,
+            2 => Self::Kiss,
+            3 => Self::Actual,
+            4 => Self::Quick,
+            _ => {
+                tracing::debug!("unknown rates type ({raw})");
+                Self::Unknown(raw)
+            }
+        })
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[non_exhaustive]
+pub struct Filters {
+    pub gyro_lowpass: Lowpass,
+    pub gyro_lowpass2: Lowpass,
+    pub dterm_lowpass: Lowpass,
+    pub dterm_lowpass2: Lowpass,
+    /// Enabled static gyro notch filters.
+    pub gyro_notches: Vec<Notch>,
+    /// The static D term notch filter, if enabled.
+    pub dterm_notch: Option<Notch>,
+    pub dynamic_notch: DynamicNotch,
+    pub rpm: RpmFilter,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[non_exhaustive]
+pub struct Lowpass {
+    pub kind: Option<FilterType>,
+    /// Static cutoff frequency, or `0` if disabled.
+    pub hz: Option<u16>,
+    /// Minimum and maximum cutoff frequencies of the dynamic lowpass.
+    pub dynamic_hz: Option<(u16, u16)>,
+}
+
+/// The type of a lowpass filter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum FilterType {
+    Pt1,
+    Biquad,
+    Pt2,
+    Pt3,
+    Unknown(u8),
+}
+
+impl FilterType {
+    fn new(raw: u8) -> Self {
+        match raw {
+            0 => Self::Pt1,
+            1 => Self::Biquad,
+            2 => Self::Pt2,
+            3 => Self::Pt3,
+            _ => {
+                tracing::debug!("unknown filter type ({raw})");
+                Self::Unknown(raw)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Notch {
+    /// Center frequency.
+    pub hz: u16,
+    /// Lower cutoff frequency.
+    pub cutoff: u16,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[non_exhaustive]
+pub struct DynamicNotch {
+    /// The number of notches per axis.
+    pub count: Option<u8>,
+    pub q: Option<u16>,
+    pub min_hz: Option<u16>,
+    pub max_hz: Option<u16>,
+    pub width_percent: Option<u8>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[non_exhaustive]
+pub struct RpmFilter {
+    /// The number of gyro notches per motor, or `0` if disabled.
+    pub harmonics: Option<u8>,
+    pub q: Option<u16>,
+    pub min_hz: Option<u16>,
+    /// Cutoff of the lowpass filter applied to motor frequencies.
+    pub lowpass_hz: Option<u16>,
+    /// The number of D term notches per motor. Removed in Betaflight 4.3.
+    pub dterm_harmonics: Option<u8>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[non_exhaustive]
+pub struct AntiGravity {
+    /// `0` for smooth, `1` for step. Removed in Betaflight 4.3.
+    pub mode: Option<u8>,
+    pub threshold: Option<u16>,
+    pub gain: Option<u16>,
+    pub cutoff_hz: Option<u16>,
+}
+
+/// Throttle PID attenuation.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[non_exhaustive]
+pub struct Tpa {
+    /// Percent reduction at full throttle.
+    pub rate: Option<u8>,
+    pub breakpoint: Option<u16>,
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct ConfigBuilder<'data> {
+    values: HashMap<&'data str, &'data str>,
+}
+
+impl<'data> ConfigBuilder<'data> {
+    /// Returns `false` if `header` is not part of the config.
+    pub(crate) fn update(&mut self, header: &'data str, value: &'data str) -> bool {
+        let known = HEADERS.contains(&header);
+        if known {
+            self.values.insert(header, value);
+        }
+        known
+    }
+
+    pub(crate) fn build(&self, firmware: FirmwareKind) -> Config {
+        let pids = [
+            self.list::<u16>(&["rollPID"]),
+            self.list(&["pitchPID"]),
+            self.list(&["yawPID"]),
+        ];
+        let d_min = self.axes::<u16>(&["d_min"]);
+        let feedforward = self.axes::<u16>(&["feedforward_weight", "ff_weight"]);
+
+        let pid = |axis: usize, pick: fn(Axes<u16>) -> u16| {
+            let gains = pids[axis].as_deref().unwrap_or_default();
+            Pid {
+                p: gains.first().copied(),
+                i: gains.get(1).copied(),
+                d: gains.get(2).copied(),
+                d_min: d_min.map(pick),
+                // INAV includes feedforward as the 4th gain
+                feedforward: feedforward.map(pick).or_else(|| gains.get(3).copied()),
+            }
+        };
+
+        let rc_expo = self.list::<u8>(&["rc_expo"]).and_then(|expo| match *expo {
+            [expo] => Some(Axes {
+                roll: expo,
+                pitch: expo,
+                yaw: self.get(&["rc_yaw_expo"])?,
+            }),
+            _ => Axes::from_slice(&expo),
+        });
+
+        Config {
+            pid: Axes {
+                roll: pid(0, |axes| axes.roll),
+                pitch: pid(1, |axes| axes.pitch),
+                yaw: pid(2, |axes| axes.yaw),
+            },
+            rates: Rates {
+                kind: self
+                    .get(&["rates_type"])
+                    .and_then(|raw| RatesType::new(raw, firmware)),
+                rc_rates: self.axes(&["rc_rates"]),
+                rc_expo,
+                rates: self.axes(&["rates"]),
+                rate_limits: self.axes(&["rate_limits"]),
+            },
+            filters: self.filters(),
+            anti_gravity: AntiGravity {
+                mode: self.get(&["anti_gravity_mode"]),
+                threshold: self.get(&["anti_gravity_threshold"]),
+                gain: self.get(&["anti_gravity_gain"]),
+                cutoff_hz: self.get(&["anti_gravity_cutoff_hz"]),
+            },
+            tpa: Tpa {
+                rate: self.get(&["tpa_rate"]),
+                breakpoint: self.get(&["tpa_breakpoint"]),
+            },
+            looptime: self.get(&["looptime"]),
+            pid_process_denom: self.get(&["pid_process_denom"]),
+        }
+    }
+
+    fn filters(&self) -> Filters {
+        let lowpass = |kind: &[&str], hz: &[&str], dynamic_hz: &[&str]| Lowpass {
+            kind: self.get(kind).map(FilterType::new),
+            hz: self.get(hz),
+            dynamic_hz: self.list::<u16>(dynamic_hz).and_then(|hz| match *hz {
+                [min, max] => Some((min, max)),
+                _ => None,
+            }),
+        };
+
+        let gyro_notches = self
+            .list::<u16>(&["gyro_notch_hz"])
+            .zip(self.list::<u16>(&["gyro_notch_cutoff"]))
+            .map(|(hz, cutoff)| {
+                hz.into_iter()
+                    .zip(cutoff)
+                    .filter(|&(hz, _)| hz > 0)
+                    .map(|(hz, cutoff)| Notch { hz, cutoff })
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        let dterm_notch = self
+            .get(&["dterm_notch_hz"])
+            .zip(self.get(&["dterm_notch_cutoff"]))
+            .filter(|&(hz, _)| hz > 0)
+            .map(|(hz, cutoff)| Notch { hz, cutoff });
+
+        Filters {
+            gyro_lowpass: lowpass(
+                &["gyro_lowpass_type", "gyro_lpf1_type", "gyro_lpf_type"],
+                &["gyro_lowpass_hz", "gyro_lpf1_static_hz", "gyro_lpf_hz"],
+                &["gyro_lowpass_dyn_hz", "gyro_lpf1_dyn_hz"],
+            ),
+            gyro_lowpass2: lowpass(
+                &["gyro_lowpass2_type", "gyro_lpf2_type"],
+                &["gyro_lowpass2_hz", "gyro_lpf2_static_hz"],
+                &[],
+            ),
+            dterm_lowpass: lowpass(
+                &["dterm_filter_type", "dterm_lpf1_type", "dterm_lpf_type"],
+                &["dterm_lowpass_hz", "dterm_lpf1_static_hz", "dterm_lpf_hz"],
+                &["dterm_lowpass_dyn_hz", "dterm_lpf1_dyn_hz"],
+            ),
+            dterm_lowpass2: lowpass(
+                &["dterm_filter2_type", "dterm_lpf2_type"],
+                &["dterm_lowpass2_hz", "dterm_lpf2_static_hz", "dterm_lpf2_hz"],
+                &[],
+            ),
+            gyro_notches,
+            dterm_notch,
+            dynamic_notch: DynamicNotch {
+                count: self.get(&["dyn_notch_count"]),
+                q: self.get(&["dyn_notch_q"]),
+                min_hz: self.get(&["dyn_notch_min_hz"]),
+                max_hz: self.get(&["dyn_notch_max_hz"]),
+                width_percent: self.get(&["dyn_notch_width_percent"]),
+            },
+            rpm: RpmFilter {
+                harmonics: self.get(&["gyro_rpm_notch_harmonics", "rpm_filter_harmonics"]),
+                q: self.get(&["gyro_rpm_notch_q", "rpm_filter_q"]),
+                min_hz: self.get(&["gyro_rpm_notch_min", "rpm_filter_min_hz"]),
+                lowpass_hz: self.get(&["rpm_notch_lpf", "rpm_filter_lpf_hz"]),
+                dterm_harmonics: self.get(&["dterm_rpm_notch_harmonics"]),
+            },
+        }
+    }
+
+    /// Returns the value of the first of `names` that is present.
+    fn raw(&self, names: &[&str]) -> Option<(&'data str, &'data str)> {
+        names
+            .iter()
+            .find_map(|&name| self.values.get_key_value(name))
+            .map(|(&name, &value)| (name, value))
+    }
+
+    fn get<T: FromStr>(&self, names: &[&str]) -> Option<T> {
+        let (name, value) = self.raw(names)?;
+        let parsed = value.trim().parse().ok();
+        if parsed.is_none() {
+            tracing::debug!("invalid value for header `{name}`: `{value}`");
+        }
+        parsed
+    }
+
+    fn list<T: FromStr>(&self, names: &[&str]) -> Option<Vec<T>> {
+        let (name, value) = self.raw(names)?;
+        let parsed = value
+            .split(',')
+            .map(|x| x.trim().parse().ok())
+            .collect::<Option<Vec<_>>>();
+        if parsed.is_none() {
+            tracing::debug!("invalid value for header `{name}`: `{value}`");
+        }
+        parsed
+    }
+
+    fn axes<T: FromStr + Copy>(&self, names: &[&str]) -> Option<Axes<T>> {
+        Axes::from_slice(&self.list(names)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec;
+
+    use super::*;
+
+    fn build(firmware: FirmwareKind, headers: &[(&'static str, &'static str)]) -> Config {
+        let mut builder = ConfigBuilder::default();
+        for &(header, value) in headers {
+            assert!(builder.update(header, value), "unknown header: {header}");
+        }
+        builder.build(firmware)
+    }
+
+    #[test]
+    fn betaflight_4_2() {
+        let config = build(
+            FirmwareKind::Betaflight,
+            &[
+                ("rollPID", "50,102,36"),
+                ("pitchPID", "55,108,38"),
+                ("yawPID", "54,108,0"),
+                ("d_min", "30,32,0"),
+                ("feedforward_weight", "97,103,97"),
+                ("rates_type", "3"),
+                ("rc_rates", "70,70,70"),
+                ("rc_expo", "0,0,0"),
+                ("rates", "75,75,75"),
+                ("gyro_lowpass_type", "0"),
+                ("gyro_lowpass_hz", "200"),
+                ("gyro_lowpass_dyn_hz", "200,500"),
+                ("gyro_notch_hz", "0,300"),
+                ("gyro_notch_cutoff", "0,200"),
+                ("dterm_notch_hz", "0"),
+                ("dterm_notch_cutoff", "0"),
+            ],
+        );
+
+        assert_eq!(
+            Pid {
+                p: Some(55),
+                i: Some(108),
+                d: Some(38),
+                d_min: Some(32),
+                feedforward: Some(103),
+            },
+            config.pid.pitch
+        );
+        assert_eq!(Some(RatesType::Actual), config.rates.kind);
+        assert_eq!(
+            Some(Axes {
+                roll: 75,
+                pitch: 75,
+                yaw: 75
+            }),
+            config.rates.rates
+        );
+
+        let gyro = config.filters.gyro_lowpass;
+        assert_eq!(Some(FilterType::Pt1), gyro.kind);
+        assert_eq!(Some(200), gyro.hz);
+        assert_eq!(Some((200, 500)), gyro.dynamic_hz);
+        assert_eq!(
+            vec![Notch {
+                hz: 300,
+                cutoff: 200
+            }],
+            config.filters.gyro_notches
+        );
+        assert_eq!(None, config.filters.dterm_notch);
+    }
+
+    #[test]
+    fn betaflight_4_3() {
+        let config = build(
+            FirmwareKind::Betaflight,
+            &[
+                ("ff_weight", "120,125,80"),
+                ("dterm_lpf1_type", "1"),
+                ("dterm_lpf1_static_hz", "75"),
+                ("rpm_filter_harmonics", "3"),
+            ],
+        );
+
+        assert_eq!(Some(80), config.pid.yaw.feedforward);
+        assert_eq!(Some(FilterType::Biquad), config.filters.dterm_lowpass.kind);
+        assert_eq!(Some(75), config.filters.dterm_lowpass.hz);
+        assert_eq!(Some(3), config.filters.rpm.harmonics);
+    }
+
+    #[test]
+    fn inav() {
+        let config = build(
+            FirmwareKind::Inav,
+            &[
+                ("rollPID", "40,30,23,60"),
+                ("rates_type", "0"),
+                ("rc_expo", "70"),
+                ("rc_yaw_expo", "20"),
+            ],
+        );
+
+        assert_eq!(Some(40), config.pid.roll.p);
+        assert_eq!(Some(60), config.pid.roll.feedforward);
+        assert_eq!(None, config.rates.kind);
+        assert_eq!(
+            Some(Axes {
+                roll: 70,
+                pitch: 70,
+                yaw: 20
+            }),
+            config.rates.rc_expo
+        );
+    }
+
+    #[test]
+    fn invalid() {
+        let config = build(
+            FirmwareKind::Betaflight,
+            &[("looptime", "x"), ("rc_rates", "70,70")],
+        );
+
+        assert_eq!(None, config.looptime);
+        assert_eq!(None, config.rates.rc_rates);
+    }
+}
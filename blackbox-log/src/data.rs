@@ -1,11 +1,15 @@
 //! Types for the data section of blackbox logs.
 
+use alloc::vec::Vec;
+use core::fmt;
+
 use crate::event::Event;
 use crate::frame::gps::{GpsFrame, RawGpsFrame};
 use crate::frame::main::{MainFrame, RawMainFrame};
 use crate::frame::slow::{RawSlowFrame, SlowFrame};
-use crate::frame::{DataFrameKind, FrameKind, GpsHomeFrame};
-use crate::parser::InternalError;
+use crate::frame::{DataFrameKind, FrameKind, GpsHomeFrame, GpsPosition};
+use crate::index::IndexEntry;
+use crate::parser::{InternalError, InternalResult};
 use crate::{Headers, Reader};
 
 /// An pseudo-event-based parser for the data section of blackbox logs.
@@ -13,10 +17,9 @@
 pub struct DataParser<'data, 'headers> {
     headers: &'headers Headers<'data>,
     data: Reader<'data>,
-    stats: Stats,
-    main_frames: MainFrameHistory,
-    gps_home_frame: Option<GpsHomeFrame>,
-    done: bool,
+    state: ParserState,
+    /// Offset of the start of the last frame returned
+    frame_offset: usize,
 }
 
 impl<'data, 'headers> DataParser<'data, 'headers> {
@@ -25,138 +28,423 @@ pub fn new(data: Reader<'data>, headers: &'headers Headers<'data>) -> Self {
         Self {
             headers,
             data,
-            stats: Stats::default(),
-            main_frames: MainFrameHistory::default(),
-            gps_home_frame: None,
-            done: false,
+            state: ParserState::default(),
+            frame_offset: 0,
         }
     }
 
+    /// Moves the parser to the intra frame at `entry`, as if it were the first
+    /// frame in the log.
+    ///
+    /// `entry` must come from an [`Index`](crate::index::Index) built from the
+    /// same log.
+    pub fn seek(&mut self, entry: &IndexEntry) {
+        self.data.seek(entry.offset);
+        self.state
+            .reset(entry.gps_home.map(GpsHomeFrame), Some(entry.time));
+    }
+
     /// Returns the current stats.
     pub fn stats(&self) -> &Stats {
-        &self.stats
+        &self.state.stats
+    }
+
+    /// Starts recording the location of each corruption found into
+    /// [`Stats::corruptions`].
+    pub fn record_corruptions(&mut self) {
+        self.state.record_corruptions();
     }
 
     /// Returns `true` if the parser has reached the end of the log.
     pub fn is_done(&self) -> bool {
-        self.done
+        self.state.done
+    }
+
+    /// Returns the offset of the start of the last frame returned.
+    pub(crate) const fn frame_offset(&self) -> usize {
+        self.frame_offset
+    }
+
+    pub(crate) fn gps_home(&self) -> Option<GpsPosition> {
+        self.state.gps_home()
     }
 
     /// Continues parsing until the next [`ParseEvent`] can be returned. Returns
     /// `None` if the parser finds the end of the log.
     pub fn next<'parser>(&'parser mut self) -> Option<ParseEvent<'data, 'headers, 'parser>> {
-        if self.done {
+        if self.state.done {
             return None;
         }
 
-        loop {
-            let byte = self.data.read_u8()?;
-            let restore = self.data.get_restore_point();
+        let (offset, frame) = self.state.next_frame(&mut self.data, self.headers)?;
+        self.frame_offset = offset;
+        Some(self.state.emit(self.headers, frame))
+    }
+}
 
-            let Some(kind) = FrameKind::from_byte(byte) else {
-                skip_to_frame(&mut self.data);
-                continue;
-            };
+/// A parser for the data section of blackbox logs that fails at the first
+/// sign of corruption, rather than skipping ahead to the next valid frame.
+///
+/// This is useful to check that a log was written correctly. Use
+/// [`DataParser`] to recover as much data as possible from damaged logs.
+#[derive(Debug)]
+pub struct StrictDataParser<'data, 'headers> {
+    headers: &'headers Headers<'data>,
+    data: Reader<'data>,
+    state: ParserState,
+}
+
+impl<'data, 'headers> StrictDataParser<'data, 'headers> {
+    /// Constructs a new parser without beginning parsing.
+    pub fn new(data: Reader<'data>, headers: &'headers Headers<'data>) -> Self {
+        Self {
+            headers,
+            data,
+            state: ParserState::default(),
+        }
+    }
 
-            tracing::trace!("trying to parse {kind:?} frame");
+    /// Returns the current stats.
+    pub fn stats(&self) -> &Stats {
+        &self.state.stats
+    }
 
-            let result = match kind {
-                FrameKind::Event => Event::parse(&mut self.data).map(InternalFrame::Event),
-                FrameKind::Data(DataFrameKind::Intra | DataFrameKind::Inter) => {
-                    RawMainFrame::parse(&mut self.data, self.headers, kind, &self.main_frames)
-                        .map(InternalFrame::Main)
-                }
-                FrameKind::Data(DataFrameKind::Slow) => self
-                    .headers
-                    .slow_frame_def
-                    .parse(&mut self.data, self.headers)
-                    .map(InternalFrame::Slow),
-                FrameKind::Data(DataFrameKind::Gps) => {
-                    self.headers.gps_frame_def.as_ref().map_or_else(
-                        || {
-                            tracing::debug!("found GPS frame without GPS frame definition");
-                            Err(InternalError::Retry)
-                        },
-                        |gps| {
-                            gps.parse(
-                                &mut self.data,
-                                self.headers,
-                                self.main_frames.last().map(|frame| frame.time),
-                                self.gps_home_frame.as_ref(),
-                            )
-                            .map(InternalFrame::Gps)
-                        },
-                    )
-                }
-                FrameKind::Data(DataFrameKind::GpsHome) => {
-                    self.headers.gps_home_frame_def.as_ref().map_or_else(
-                        || {
-                            tracing::debug!(
-                                "found GPS home frame without GPS home frame definition"
-                            );
-                            Err(InternalError::Retry)
-                        },
-                        |gps_home| {
-                            gps_home
-                                .parse(&mut self.data, self.headers)
-                                .map(InternalFrame::GpsHome)
-                        },
+    /// Returns `true` if the parser has reached the end of the log or returned
+    /// an error.
+    pub fn is_done(&self) -> bool {
+        self.state.done
+    }
+
+    /// Continues parsing until the next [`ParseEvent`] can be returned. Returns
+    /// `Ok(None)` after the `End of log` event.
+    ///
+    /// Once an error has been returned, all further calls return `Ok(None)`.
+    pub fn next<'parser>(
+        &'parser mut self,
+    ) -> DataResult<Option<ParseEvent<'data, 'headers, 'parser>>> {
+        if self.state.done {
+            return Ok(None);
+        }
+
+        let frame = match self
+            .state
+            .try_next_frame(&mut self.data, self.headers, true)
+        {
+            Ok(Some((_, frame))) => frame,
+            Ok(None) => {
+                tracing::debug!("found end of data section without end of log event");
+                self.state.done = true;
+                return Err(DataError::MissingEnd);
+            }
+            Err(corruption) => {
+                self.state.done = true;
+                return Err(DataError::Corrupted(corruption));
+            }
+        };
+
+        Ok(Some(self.state.emit(self.headers, frame)))
+    }
+}
+
+/// Parser state shared by all data section parsers, independent of where the
+/// data is read from.
+#[derive(Debug, Default)]
+pub(crate) struct ParserState {
+    pub(crate) stats: Stats,
+    main_frames: MainFrameHistory,
+    gps_home_frame: Option<GpsHomeFrame>,
+    /// Set after a `Resume` event until the next intra frame, since inter
+    /// frames cannot be predicted without a previous main frame
+    awaiting_intra: bool,
+    /// Time and iteration of the last main frame, kept across `Resume` events
+    last_main: Option<(u64, u32)>,
+    pub(crate) done: bool,
+}
+
+impl ParserState {
+    /// Attempts to parse a single frame of type `kind` starting at `offset`.
+    /// The frame kind byte must already have been consumed.
+    pub(crate) fn parse_frame(
+        &self,
+        data: &mut Reader,
+        headers: &Headers,
+        offset: usize,
+        kind: FrameKind,
+    ) -> InternalResult<InternalFrame> {
+        tracing::trace!("trying to parse {kind:?} frame");
+
+        match kind {
+            FrameKind::Event => {
+                let event = Event::parse(
+                    data,
+                    headers.firmware_kind,
+                    headers.firmware_version.as_ref(),
+                    self.main_frames.last_time(),
+                )?;
+                let (time, iteration) = self.last_main.unzip();
+
+                Ok(InternalFrame::Event(EventFrame {
+                    event,
+                    time,
+                    iteration,
+                    offset,
+                }))
+            }
+            FrameKind::Data(DataFrameKind::Inter) if self.awaiting_intra => {
+                tracing::debug!("found inter frame after resume without an intra frame");
+                Err(InternalError::Retry)
+            }
+            FrameKind::Data(DataFrameKind::Intra | DataFrameKind::Inter) => {
+                RawMainFrame::parse(data, headers, kind, &self.main_frames).map(InternalFrame::Main)
+            }
+            FrameKind::Data(DataFrameKind::Slow) => headers
+                .slow_frame_def
+                .parse(data, headers)
+                .map(InternalFrame::Slow),
+            FrameKind::Data(DataFrameKind::Gps) => headers.gps_frame_def.as_ref().map_or_else(
+                || {
+                    tracing::debug!("found GPS frame without GPS frame definition");
+                    Err(InternalError::Retry)
+                },
+                |gps| {
+                    gps.parse(
+                        data,
+                        headers,
+                        &self.main_frames,
+                        self.gps_home_frame.as_ref(),
                     )
+                    .map(InternalFrame::Gps)
+                },
+            ),
+            FrameKind::Data(DataFrameKind::GpsHome) => {
+                headers.gps_home_frame_def.as_ref().map_or_else(
+                    || {
+                        tracing::debug!("found GPS home frame without GPS home frame definition");
+                        Err(InternalError::Retry)
+                    },
+                    |gps_home| gps_home.parse(data, headers).map(InternalFrame::GpsHome),
+                )
+            }
+        }
+    }
+
+    /// Finds the next valid frame and its offset, skipping any corrupted data.
+    ///
+    /// GPS home frames are applied immediately and never returned. All other
+    /// frames must be passed to [`ParserState::emit`] before calling this
+    /// again.
+    pub(crate) fn next_frame(
+        &mut self,
+        data: &mut Reader,
+        headers: &Headers,
+    ) -> Option<(usize, InternalFrame)> {
+        self.try_next_frame(data, headers, false).unwrap_or(None)
+    }
+
+    /// Like [`ParserState::next_frame`], but if `strict` is set, returns the
+    /// first corruption found instead of skipping it.
+    fn try_next_frame(
+        &mut self,
+        data: &mut Reader,
+        headers: &Headers,
+        strict: bool,
+    ) -> Result<Option<(usize, InternalFrame)>, Corruption> {
+        loop {
+            let offset = data.position();
+            let Some(byte) = data.read_u8() else {
+                return Ok(None);
+            };
+            let restore = data.get_restore_point();
+
+            let Some(kind) = FrameKind::from_byte(byte) else {
+                let corruption = self.corrupted(offset, None, CorruptionReason::InvalidFrameKind);
+                if strict {
+                    return Err(corruption);
                 }
+
+                skip_to_frame(data);
+                self.stats.skipped_bytes += data.position() - offset;
+                continue;
             };
 
+            let result = self.parse_frame(data, headers, offset, kind);
+
             match result {
                 // Check for a good frame kind byte, or EOF
-                Ok(frame)
-                    if self
-                        .data
-                        .peek()
-                        .map_or(true, |byte| FrameKind::from_byte(byte).is_some()) =>
-                {
-                    match frame {
-                        InternalFrame::Event(event) => {
-                            if matches!(event, Event::End { .. }) {
-                                self.done = true;
-                            }
-
-                            self.stats.counts.event += 1;
-                            return Some(ParseEvent::Event(event));
-                        }
-                        InternalFrame::Main(main) => {
-                            self.stats.counts.main += 1;
-                            let main = self.main_frames.push(main);
-
-                            return Some(ParseEvent::Main(MainFrame::new(self.headers, main)));
-                        }
-                        InternalFrame::Slow(slow) => {
-                            self.stats.counts.slow += 1;
-                            return Some(ParseEvent::Slow(SlowFrame::new(self.headers, slow)));
-                        }
-                        InternalFrame::Gps(gps) => {
-                            self.stats.counts.gps += 1;
-                            return Some(ParseEvent::Gps(GpsFrame::new(self.headers, gps)));
-                        }
-                        InternalFrame::GpsHome(gps_home) => {
-                            self.stats.counts.gps_home += 1;
-                            self.gps_home_frame = Some(gps_home);
-                            continue;
-                        }
+                Ok(frame) if data.peek().map_or(true, is_frame_kind) => {
+                    if let InternalFrame::GpsHome(gps_home) = frame {
+                        self.update_gps_home(gps_home);
+                        continue;
                     }
+
+                    return Ok(Some((offset, frame)));
                 }
                 Ok(_) | Err(InternalError::Retry) => {
                     tracing::debug!("found corrupted {kind:?} frame");
-                    self.data.restore(restore);
-                    skip_to_frame(&mut self.data);
+                    let reason = if result.is_ok() {
+                        CorruptionReason::BadTerminator
+                    } else {
+                        CorruptionReason::InvalidFrame
+                    };
+                    let corruption = self.corrupted(offset, Some(kind), reason);
+                    if strict {
+                        return Err(corruption);
+                    }
+
+                    data.restore(restore);
+                    skip_to_frame(data);
+                    self.stats.skipped_bytes += data.position() - offset;
                 }
                 Err(InternalError::Eof) => {
                     tracing::debug!("found unexpected end of file in data section");
-                    return None;
+                    let corruption =
+                        self.corrupted(offset, Some(kind), CorruptionReason::UnexpectedEof);
+                    self.stats.skipped_bytes += data.position() + data.remaining() - offset;
+                    return if strict { Err(corruption) } else { Ok(None) };
+                }
+            }
+        }
+    }
+
+    /// Clears all state from previous frames, except for the stats. `time`
+    /// is used to expand the timestamp of the next main frame.
+    pub(crate) fn reset(&mut self, gps_home: Option<GpsHomeFrame>, time: Option<u64>) {
+        self.main_frames = MainFrameHistory::starting_at(time);
+        self.gps_home_frame = gps_home;
+        self.awaiting_intra = false;
+        self.last_main = None;
+        self.done = false;
+    }
+
+    /// Discards the main frame history after logging was paused, since the
+    /// next main frame is not predicted from those before the pause.
+    fn resume(&mut self, iteration: u32, time: u64) {
+        self.stats.resumes += 1;
+
+        if let Some(last) = self.main_frames.last() {
+            let iterations = iteration.saturating_sub(last.iteration);
+            let time = time.saturating_sub(last.time);
+            tracing::debug!("logging resumed after {iterations} iterations, {time}us");
+
+            self.stats.paused_iterations += u64::from(iterations);
+            self.stats.paused_time += time;
+        }
+
+        self.main_frames = MainFrameHistory::starting_at(Some(time));
+        self.awaiting_intra = true;
+    }
+
+    pub(crate) fn gps_home(&self) -> Option<GpsPosition> {
+        self.gps_home_frame.as_ref().map(|home| home.0)
+    }
+
+    pub(crate) fn record_corruptions(&mut self) {
+        self.stats.corruptions.get_or_insert_with(Vec::new);
+    }
+
+    /// Records a corrupted frame starting at `offset`. Any bytes skipped must
+    /// be added to [`Stats::skipped_bytes`] separately.
+    pub(crate) fn corrupted(
+        &mut self,
+        offset: usize,
+        kind: Option<FrameKind>,
+        reason: CorruptionReason,
+    ) -> Corruption {
+        let corruption = Corruption {
+            offset,
+            kind,
+            reason,
+        };
+
+        if let Some(kind) = kind {
+            *self.stats.corrupted.get_mut(kind) += 1;
+        }
+
+        if let Some(corruptions) = &mut self.stats.corruptions {
+            corruptions.push(corruption);
+        }
+
+        corruption
+    }
+
+    pub(crate) fn update_gps_home(&mut self, gps_home: GpsHomeFrame) {
+        self.stats.counts.gps_home += 1;
+        self.gps_home_frame = Some(gps_home);
+    }
+
+    /// Records a valid frame and converts it into a [`ParseEvent`].
+    ///
+    /// # Panics
+    ///
+    /// GPS home frames do not produce an event and must be passed to
+    /// [`ParserState::update_gps_home`] instead.
+    pub(crate) fn emit<'data, 'headers>(
+        &mut self,
+        headers: &'headers Headers<'data>,
+        frame: InternalFrame,
+    ) -> ParseEvent<'data, 'headers, '_> {
+        match frame {
+            InternalFrame::Event(event) => {
+                match event.event {
+                    Event::End { .. } => self.done = true,
+                    Event::Resume {
+                        log_iteration,
+                        time,
+                    } => self.resume(log_iteration, time),
+                    _ => {}
                 }
+
+                self.stats.counts.event += 1;
+                ParseEvent::Event(event)
+            }
+            InternalFrame::Main(main) => {
+                self.stats.counts.main += 1;
+                self.awaiting_intra &= !main.intra;
+                self.last_main = Some((main.time, main.iteration));
+                let main = self.main_frames.push(main);
+
+                ParseEvent::Main(MainFrame::new(headers, main))
+            }
+            InternalFrame::Slow(slow) => {
+                self.stats.counts.slow += 1;
+                ParseEvent::Slow(SlowFrame::new(headers, slow))
             }
+            InternalFrame::Gps(gps) => {
+                self.stats.counts.gps += 1;
+                ParseEvent::Gps(GpsFrame::new(headers, gps))
+            }
+            InternalFrame::GpsHome(_) => unreachable!("GPS home frames are not emitted"),
         }
     }
 }
 
+pub type DataResult<T> = Result<T, DataError>;
+
+/// An error found by a [`StrictDataParser`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[non_exhaustive]
+pub enum DataError {
+    /// A frame was corrupted or truncated.
+    Corrupted(Corruption),
+    /// The data section ended without an `End of log` event.
+    MissingEnd,
+}
+
+impl fmt::Display for DataError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Corrupted(corruption) => corruption.fmt(f),
+            Self::MissingEnd => write!(f, "data section ended without an end of log event"),
+        }
+    }
+}
+
+// TODO: waiting on https://github.com/rust-lang/rust-clippy/pull/9545 to land
+#[allow(clippy::std_instead_of_core)]
+#[cfg(feature = "std")]
+impl std::error::Error for DataError {}
+
 /// Statistics about a decoded log.
 #[derive(Debug, Clone, Default)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize))]
@@ -164,6 +452,21 @@ pub fn next<'parser>(&'parser mut self) -> Option<ParseEvent<'data, 'headers, 'p
 pub struct Stats {
     /// The number of valid frames found of each type.
     pub counts: FrameCounts,
+    /// The number of corrupted frames found of each type.
+    pub corrupted: FrameCounts,
+    /// The total number of bytes skipped while recovering from corrupted
+    /// frames.
+    pub skipped_bytes: usize,
+    /// Each corrupted frame in the order found. This is only recorded once
+    /// enabled using [`DataParser::record_corruptions`], or when decoding with
+    /// `parallel::decode_recording_corruptions`.
+    pub corruptions: Option<Vec<Corruption>>,
+    /// The number of times logging was resumed after being paused.
+    pub resumes: usize,
+    /// The total number of loop iterations not logged while paused.
+    pub paused_iterations: u64,
+    /// The total time not logged while paused, in microseconds.
+    pub paused_time: u64,
 }
 
 #[derive(Debug, Clone, Default)]
@@ -176,16 +479,102 @@ pub struct FrameCounts {
     pub gps_home: usize,
 }
 
+impl FrameCounts {
+    fn get_mut(&mut self, kind: FrameKind) -> &mut usize {
+        match kind {
+            FrameKind::Event => &mut self.event,
+            FrameKind::Data(DataFrameKind::Intra | DataFrameKind::Inter) => &mut self.main,
+            FrameKind::Data(DataFrameKind::Slow) => &mut self.slow,
+            FrameKind::Data(DataFrameKind::Gps) => &mut self.gps,
+            FrameKind::Data(DataFrameKind::GpsHome) => &mut self.gps_home,
+        }
+    }
+}
+
+/// The location of a corrupted frame in the data section.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Corruption {
+    /// Byte offset of the start of the frame, from the start of the log.
+    pub offset: usize,
+    /// The kind of frame that failed to parse, or `None` if the byte at
+    /// `offset` does not start any kind of frame.
+    pub kind: Option<FrameKind>,
+    pub reason: CorruptionReason,
+}
+
+impl fmt::Display for Corruption {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Self {
+            offset,
+            kind,
+            reason,
+        } = self;
+
+        match (kind, reason) {
+            (Some(kind), CorruptionReason::InvalidFrame) => {
+                write!(f, "invalid {kind} frame at byte {offset}")
+            }
+            (Some(kind), CorruptionReason::BadTerminator) => write!(
+                f,
+                "{kind} frame at byte {offset} is not followed by another frame"
+            ),
+            (Some(kind), CorruptionReason::UnexpectedEof) => {
+                write!(f, "unexpected end of file in {kind} frame at byte {offset}")
+            }
+            (None, _) | (_, CorruptionReason::InvalidFrameKind) => {
+                write!(f, "invalid frame kind at byte {offset}")
+            }
+        }
+    }
+}
+
+/// Why a frame was considered corrupted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum CorruptionReason {
+    /// The first byte is not a valid frame kind.
+    InvalidFrameKind,
+    /// The frame could not be decoded, eg an invalid event byte or a frame
+    /// kind missing its definition in the headers.
+    InvalidFrame,
+    /// The frame was not followed by the start of another frame or the end of
+    /// the log.
+    BadTerminator,
+    /// The log ended partway through the frame.
+    UnexpectedEof,
+}
+
 #[derive(Debug)]
 pub enum ParseEvent<'data, 'headers, 'parser> {
-    Event(Event),
+    Event(EventFrame),
     Main(MainFrame<'data, 'headers, 'parser>),
     Slow(SlowFrame<'data, 'headers>),
     Gps(GpsFrame<'data, 'headers>),
 }
 
+/// An [`Event`] along with where it was found in the log.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct EventFrame {
+    pub event: Event,
+    /// Time of the most recent main frame before the event, in microseconds.
+    pub time: Option<u64>,
+    /// `loopIteration` of the most recent main frame before the event.
+    pub iteration: Option<u32>,
+    /// Byte offset of the start of the event frame, from the start of the log.
+    pub offset: usize,
+}
+
+#[inline]
+pub(crate) fn is_frame_kind(byte: u8) -> bool {
+    FrameKind::from_byte(byte).is_some()
+}
+
+/// Advances to the next byte that could start a frame, returning `false` if
+/// none was found.
 #[cold]
-fn skip_to_frame(data: &mut Reader) {
+pub(crate) fn skip_to_frame(data: &mut Reader) -> bool {
     data.skip_until_any(
         &[
             FrameKind::Event,
@@ -195,23 +584,35 @@ fn skip_to_frame(data: &mut Reader) {
             FrameKind::Data(DataFrameKind::GpsHome),
         ]
         .map(u8::from),
-    );
+    )
 }
 
 #[derive(Debug, Default)]
 pub(crate) struct MainFrameHistory {
     history: [Option<RawMainFrame>; 2],
     index_new: usize,
+    /// Time of the last main frame or `Resume` event, used to expand the 32
+    /// bit timestamps in later frames
+    last_time: Option<u64>,
 }
 
 impl MainFrameHistory {
+    /// Creates an empty history that expands timestamps relative to `time`.
+    pub(crate) fn starting_at(time: Option<u64>) -> Self {
+        Self {
+            last_time: time,
+            ..Self::default()
+        }
+    }
+
     #[inline(always)]
     fn index_old(&self) -> usize {
         (self.index_new + 1) % self.history.len()
     }
 
-    fn push(&mut self, frame: RawMainFrame) -> &RawMainFrame {
+    pub(crate) fn push(&mut self, frame: RawMainFrame) -> &RawMainFrame {
         self.index_new = self.index_old();
+        self.last_time = Some(frame.time);
         self.history[self.index_new] = Some(frame);
         self.last().unwrap()
     }
@@ -223,13 +624,296 @@ pub(crate) fn last(&self) -> Option<&RawMainFrame> {
     pub(crate) fn last_last(&self) -> Option<&RawMainFrame> {
         self.history[self.index_old()].as_ref()
     }
+
+    pub(crate) const fn last_time(&self) -> Option<u64> {
+        self.last_time
+    }
 }
 
-#[derive(Debug)]
-enum InternalFrame {
-    Event(Event),
+#[derive(Debug, Clone)]
+pub(crate) enum InternalFrame {
+    Event(EventFrame),
     Main(RawMainFrame),
     Slow(RawSlowFrame),
     Gps(RawGpsFrame),
     GpsHome(GpsHomeFrame),
 }
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec;
+
+    use test_case::case;
+
+    use super::*;
+    use crate::units::DisarmReason;
+    use crate::writer::Writer;
+
+    const EVENT: FrameKind = FrameKind::Event;
+    const INTRA: FrameKind = FrameKind::Data(DataFrameKind::Intra);
+
+    #[test]
+    fn corruptions() {
+        let log = include_bytes!("../tests/logs/gps.bbl");
+        let headers = Headers::parse(&mut Reader::new(log)).unwrap();
+
+        let mut writer = Writer::new(&headers);
+        let start = writer.as_bytes().len();
+        writer.write_intra(0, 0, &[1]).unwrap();
+        let event = writer.as_bytes().len();
+        writer.write_intra(32, 1_000, &[1]).unwrap();
+
+        let mut log = writer.into_bytes();
+        log.push(b'I');
+        log.splice(event..event, *b"E\x7F");
+        log.insert(start, 0);
+
+        let mut data = Reader::new(&log);
+        let headers = Headers::parse(&mut data).unwrap();
+        let mut parser = DataParser::new(data, &headers);
+        parser.record_corruptions();
+        while parser.next().is_some() {}
+
+        let stats = parser.stats();
+        assert_eq!(2, stats.counts.main);
+        assert_eq!(1, stats.corrupted.event);
+        assert_eq!(1, stats.corrupted.main);
+        assert_eq!(4, stats.skipped_bytes);
+
+        let corruption = |offset, kind, reason| Corruption {
+            offset,
+            kind,
+            reason,
+        };
+        let expected = vec![
+            corruption(start, None, CorruptionReason::InvalidFrameKind),
+            corruption(event + 1, Some(EVENT), CorruptionReason::InvalidFrame),
+            corruption(log.len() - 1, Some(INTRA), CorruptionReason::UnexpectedEof),
+        ];
+        assert_eq!(Some(expected), stats.corruptions);
+    }
+
+    #[test]
+    fn strict_valid() {
+        let log = include_bytes!("../tests/logs/gps.bbl");
+        let mut data = Reader::new(log);
+        let headers = Headers::parse(&mut data).unwrap();
+
+        let mut parser = DataParser::new(data.clone(), &headers);
+        while parser.next().is_some() {}
+
+        let mut strict = StrictDataParser::new(data, &headers);
+        while strict.next().unwrap().is_some() {}
+
+        assert!(strict.is_done());
+        assert_eq!(parser.stats().counts.main, strict.stats().counts.main);
+        assert_eq!(parser.stats().counts.gps, strict.stats().counts.gps);
+    }
+
+    #[case(b"", None; "missing end")]
+    #[case(b"\x00", Some((false, INTRA, CorruptionReason::BadTerminator)); "bad terminator")]
+    #[case(b"E\x7F", Some((true, EVENT, CorruptionReason::InvalidFrame)); "invalid event")]
+    #[case(b"I", Some((true, INTRA, CorruptionReason::UnexpectedEof)); "truncated")]
+    fn strict_errors(suffix: &[u8], expected: Option<(bool, FrameKind, CorruptionReason)>) {
+        let log = include_bytes!("../tests/logs/gps.bbl");
+        let headers = Headers::parse(&mut Reader::new(log)).unwrap();
+
+        let mut writer = Writer::new(&headers);
+        let start = writer.as_bytes().len();
+        writer.write_intra(0, 0, &[1]).unwrap();
+        let end = writer.as_bytes().len();
+
+        let mut log = writer.into_bytes();
+        log.extend_from_slice(suffix);
+
+        let mut data = Reader::new(&log);
+        let headers = Headers::parse(&mut data).unwrap();
+        let mut parser = StrictDataParser::new(data, &headers);
+
+        let error = loop {
+            match parser.next() {
+                Ok(Some(_)) => {}
+                Ok(None) => panic!("expected an error"),
+                Err(err) => break err,
+            }
+        };
+
+        let expected = expected.map_or(DataError::MissingEnd, |(after, kind, reason)| {
+            DataError::Corrupted(Corruption {
+                offset: if after { end } else { start },
+                kind: Some(kind),
+                reason,
+            })
+        });
+        assert_eq!(expected, error);
+        assert!(parser.is_done());
+        assert!(parser.next().unwrap().is_none());
+    }
+
+    #[test]
+    fn resume() {
+        let log = include_bytes!("../tests/logs/gps.bbl");
+        let headers = Headers::parse(&mut Reader::new(log)).unwrap();
+
+        let mut resume = Writer::new(&headers);
+        let start = resume.as_bytes().len();
+        resume
+            .write_event(&Event::Resume {
+                log_iteration: 320,
+                time: 10_000,
+            })
+            .unwrap();
+
+        let mut writer = Writer::new(&headers);
+        writer.write_intra(0, 0, &[1]).unwrap();
+        writer.write_inter(2, 10, &[2]).unwrap();
+        let paused = writer.as_bytes().len();
+        // Cannot be decoded after the resume without a preceding intra frame
+        writer.write_inter(4, 20, &[9]).unwrap();
+        writer.write_intra(320, 10_000, &[3]).unwrap();
+        writer.write_inter(322, 10_010, &[4]).unwrap();
+        writer
+            .write_event(&Event::End {
+                disarm_reason: None,
+            })
+            .unwrap();
+
+        let mut log = writer.into_bytes();
+        log.splice(paused..paused, resume.as_bytes()[start..].iter().copied());
+
+        let mut data = Reader::new(&log);
+        let headers = Headers::parse(&mut data).unwrap();
+        let mut parser = DataParser::new(data, &headers);
+
+        let mut frames = Vec::new();
+        while let Some(event) = parser.next() {
+            if let ParseEvent::Main(main) = event {
+                frames.push((main.raw.iteration, main.raw.time, main.raw.values[0]));
+            }
+        }
+
+        assert_eq!(
+            vec![(0, 0, 1), (2, 10, 2), (320, 10_000, 3), (322, 10_010, 4)],
+            frames
+        );
+
+        let stats = parser.stats();
+        assert_eq!(1, stats.corrupted.main);
+        assert_eq!(1, stats.resumes);
+        assert_eq!(318, stats.paused_iterations);
+        assert_eq!(9_990, stats.paused_time);
+    }
+
+    #[test]
+    fn event_timing() {
+        let log = include_bytes!("../tests/logs/gps.bbl");
+        let headers = Headers::parse(&mut Reader::new(log)).unwrap();
+
+        let mut writer = Writer::new(&headers);
+        let mut offsets = Vec::new();
+        let mut write_event = |writer: &mut Writer, event| {
+            offsets.push(writer.as_bytes().len());
+            writer.write_event(&event).unwrap();
+        };
+
+        write_event(&mut writer, Event::SyncBeep(0));
+        writer.write_intra(0, 0, &[1]).unwrap();
+        writer.write_inter(2, 10, &[1]).unwrap();
+        write_event(&mut writer, Event::Disarm(DisarmReason::Switch));
+        write_event(
+            &mut writer,
+            Event::Resume {
+                log_iteration: 320,
+                time: 10_000,
+            },
+        );
+        writer.write_intra(320, 10_000, &[1]).unwrap();
+        write_event(
+            &mut writer,
+            Event::End {
+                disarm_reason: None,
+            },
+        );
+        let log = writer.into_bytes();
+
+        let mut data = Reader::new(&log);
+        let headers = Headers::parse(&mut data).unwrap();
+        let mut parser = DataParser::new(data, &headers);
+
+        let mut timings = Vec::new();
+        while let Some(event) = parser.next() {
+            if let ParseEvent::Event(event) = event {
+                timings.push((event.time, event.iteration, event.offset));
+            }
+        }
+
+        let expected = vec![
+            (None, None, offsets[0]),
+            (Some(10), Some(2), offsets[1]),
+            (Some(10), Some(2), offsets[2]),
+            (Some(10_000), Some(320), offsets[3]),
+        ];
+        assert_eq!(expected, timings);
+    }
+
+    #[test]
+    fn time_rollover() {
+        const WRAP: u64 = 1 << 32;
+        let resumed = WRAP + 0x7000_0000;
+
+        let log = include_bytes!("../tests/logs/gps.bbl");
+        let headers = Headers::parse(&mut Reader::new(log)).unwrap();
+
+        let mut writer = Writer::new(&headers);
+        writer.write_gps_home(0, 0).unwrap();
+        writer.write_intra(0, WRAP - 20, &[1]).unwrap();
+        writer.write_inter(2, WRAP - 10, &[1]).unwrap();
+        writer.write_gps(WRAP + 5, &[8, 0, 0, 0, 0, 0]).unwrap();
+        writer.write_event(&Event::SyncBeep(WRAP + 1)).unwrap();
+        writer.write_intra(32, WRAP + 10, &[1]).unwrap();
+        writer.write_inter(34, WRAP + 20, &[1]).unwrap();
+        writer
+            .write_event(&Event::Resume {
+                log_iteration: 64,
+                time: resumed,
+            })
+            .unwrap();
+        writer.write_intra(64, resumed + 10, &[1]).unwrap();
+        writer
+            .write_event(&Event::End {
+                disarm_reason: None,
+            })
+            .unwrap();
+        let log = writer.into_bytes();
+
+        let mut data = Reader::new(&log);
+        let headers = Headers::parse(&mut data).unwrap();
+        let mut parser = DataParser::new(data, &headers);
+
+        let mut times = Vec::new();
+        while let Some(event) = parser.next() {
+            match event {
+                ParseEvent::Main(main) => times.push(main.raw.time),
+                ParseEvent::Gps(gps) => times.push(gps.raw.time),
+                ParseEvent::Event(EventFrame {
+                    event: Event::SyncBeep(time) | Event::Resume { time, .. },
+                    ..
+                }) => times.push(time),
+                _ => {}
+            }
+        }
+
+        let expected = vec![
+            WRAP - 20,
+            WRAP - 10,
+            WRAP + 5,
+            WRAP + 1,
+            WRAP + 10,
+            WRAP + 20,
+            resumed,
+            resumed + 10,
+        ];
+        assert_eq!(expected, times);
+        assert_eq!(resumed - WRAP - 20, parser.stats().paused_time);
+    }
+}
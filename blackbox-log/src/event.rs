@@ -1,8 +1,16 @@
 //! Types for blackbox log events.
 
+use alloc::borrow::ToOwned;
+use alloc::vec::Vec;
+use core::fmt;
+
 use tracing::instrument;
 
-use crate::parser::{decode, InternalError, InternalResult};
+use crate::frame::FrameKind;
+use crate::headers::{FirmwareKind, FirmwareVersion};
+use crate::parser::{decode, encode, expand_time, truncate_time, InternalError, InternalResult};
+use crate::units::{DisarmReason, FlagLayout, FlightModeSet};
+use crate::writer::{WriteError, WriteResult};
 use crate::Reader;
 
 /// A decoded blackbox log event.
@@ -10,29 +18,65 @@
 #[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum Event {
     SyncBeep(u64),
+    /// Start of a cycle of the legacy autotune mode.
+    AutotuneCycleStart {
+        phase: u8,
+        cycle: u8,
+        rising: bool,
+        p: u8,
+        i: u8,
+        d: u8,
+    },
+    /// Result of a cycle of the legacy autotune mode.
+    AutotuneCycleResult {
+        overshot: bool,
+        timed_out: bool,
+        p: u8,
+        i: u8,
+        d: u8,
+    },
+    /// Target angles of the legacy autotune mode. `current_angle` and both peak
+    /// angles are in tenths of a degree, while the targets are in degrees.
+    AutotuneTargets {
+        current_angle: i16,
+        target_angle: i8,
+        target_angle_at_peak: i8,
+        first_peak_angle: i16,
+        second_peak_angle: i16,
+    },
+    /// Result of G-Tune for one axis.
+    GtuneResult {
+        axis: u8,
+        gyro_average: i32,
+        new_p: i16,
+    },
     InflightAdjustment {
-        function: u8,
+        function: AdjustmentFunction,
         new_value: AdjustedValue,
     },
     Resume {
         log_iteration: u32,
-        time: u32,
+        time: u64,
     },
-    Disarm(u32),
+    Disarm(DisarmReason),
     FlightMode {
-        flags: u32,
-        last_flags: u32,
+        flags: FlightModeSet,
+        last_flags: FlightModeSet,
     },
     ImuFailure {
         error: u32,
     },
     End {
-        disarm_reason: Option<u32>,
+        disarm_reason: Option<DisarmReason>,
     },
 }
 
 /// A new value decoded from an
 /// [`InflightAdjustment`](`Event::InflightAdjustment`) event.
+///
+/// This is the value exactly as logged, in whatever fixed-point scale the
+/// firmware stores the adjusted setting (eg Betaflight logs an RC rate of 1.2
+/// as `Int(120)`). Only old firmware logs `Float`s.
 #[derive(Debug, Clone, Copy, PartialEq)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum AdjustedValue {
@@ -40,9 +84,365 @@ pub enum AdjustedValue {
     Int(i32),
 }
 
+/// The setting changed by an
+/// [`InflightAdjustment`](`Event::InflightAdjustment`) event.
+///
+/// `F` is feedforward for Betaflight, and FF for INAV.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum AdjustmentFunction {
+    RcRate,
+    RcExpo,
+    RcYawExpo,
+    ThrottleExpo,
+    PitchRollRate,
+    YawRate,
+    PitchRollP,
+    PitchRollI,
+    PitchRollD,
+    PitchRollF,
+    /// D setpoint weight, replaced by [`PitchRollF`](Self::PitchRollF) in
+    /// Betaflight 4.0.
+    DSetpoint,
+    /// Replaced by [`FeedforwardTransition`](Self::FeedforwardTransition) in
+    /// Betaflight 4.0.
+    DSetpointTransition,
+    YawP,
+    YawI,
+    YawD,
+    YawF,
+    RateProfile,
+    PitchRate,
+    RollRate,
+    PitchP,
+    PitchI,
+    PitchD,
+    PitchF,
+    RollP,
+    RollI,
+    RollD,
+    RollF,
+    RcRateYaw,
+    FeedforwardTransition,
+    HorizonStrength,
+    RollRcRate,
+    PitchRcRate,
+    RollRcExpo,
+    PitchRcExpo,
+    PidAudio,
+    OsdProfile,
+    LedProfile,
+    LedDimmer,
+    ManualRcExpo,
+    ManualRcYawExpo,
+    ManualPitchRollRate,
+    ManualPitchRate,
+    ManualRollRate,
+    ManualYawRate,
+    PitchBoardAlignment,
+    RollBoardAlignment,
+    LevelP,
+    LevelI,
+    LevelD,
+    PosXyP,
+    PosXyI,
+    PosXyD,
+    PosZP,
+    PosZI,
+    PosZD,
+    HeadingP,
+    VelXyP,
+    VelXyI,
+    VelXyD,
+    VelZP,
+    VelZI,
+    VelZD,
+    FwMinThrottleDownPitchAngle,
+    VtxPowerLevel,
+    Tpa,
+    TpaBreakpoint,
+    NavFwControlSmoothness,
+    FwTpaTimeConstant,
+    FwLevelTrim,
+    NavWpMultiMissionIndex,
+    /// An adjustment that is not known for the firmware, with its raw index.
+    Unknown(u8),
+}
+
+impl AdjustmentFunction {
+    pub(crate) fn new(raw: u8, firmware: FirmwareKind, version: Option<&FirmwareVersion>) -> Self {
+        usize::from(raw)
+            .checked_sub(1)
+            .and_then(|index| Self::mapping(firmware, version).get(index))
+            .copied()
+            .unwrap_or_else(|| {
+                tracing::debug!("unknown adjustment function ({raw})");
+                Self::Unknown(raw)
+            })
+    }
+
+    fn to_raw(self, firmware: FirmwareKind, version: Option<&FirmwareVersion>) -> Option<u8> {
+        if let Self::Unknown(raw) = self {
+            return Some(raw);
+        }
+
+        let index = Self::mapping(firmware, version)
+            .iter()
+            .position(|&f| f == self)?;
+        u8::try_from(index + 1).ok()
+    }
+
+    /// Returns the functions in order, starting from index 1, since 0 is no
+    /// adjustment.
+    ///
+    /// Only the adjustments shared with Betaflight 3.x are known for EmuFlight
+    /// & Cleanflight, and none for Rotorflight, which has its own adjustments
+    /// for helicopters. Any others are decoded as [`Unknown`](Self::Unknown).
+    fn mapping(firmware: FirmwareKind, version: Option<&FirmwareVersion>) -> &'static [Self] {
+        // Assume anything without a parsable version is recent
+        let legacy = version.map_or(false, |version| !version.is_at_least(4, 0, 0));
+
+        match firmware {
+            FirmwareKind::Betaflight | FirmwareKind::Unknown if legacy => BETAFLIGHT_3_ADJUSTMENTS,
+            FirmwareKind::Betaflight | FirmwareKind::Unknown => BETAFLIGHT_ADJUSTMENTS,
+            // Forked from Betaflight 3.5, before its adjustments diverged
+            FirmwareKind::EmuFlight => &BETAFLIGHT_3_ADJUSTMENTS[..21],
+            // Betaflight 3.x added `RcRateYaw` after these
+            FirmwareKind::Cleanflight => &BETAFLIGHT_3_ADJUSTMENTS[..20],
+            FirmwareKind::Inav => INAV_ADJUSTMENTS,
+            FirmwareKind::Rotorflight => &[],
+        }
+    }
+}
+
+/// `adjustmentFunction_e` in Betaflight 3.x
+const BETAFLIGHT_3_ADJUSTMENTS: &[AdjustmentFunction] = {
+    use AdjustmentFunction::*;
+    &[
+        RcRate,
+        RcExpo,
+        ThrottleExpo,
+        PitchRollRate,
+        YawRate,
+        PitchRollP,
+        PitchRollI,
+        PitchRollD,
+        YawP,
+        YawI,
+        YawD,
+        RateProfile,
+        PitchRate,
+        RollRate,
+        PitchP,
+        PitchI,
+        PitchD,
+        RollP,
+        RollI,
+        RollD,
+        RcRateYaw,
+        DSetpoint,
+        DSetpointTransition,
+        HorizonStrength,
+        RollRcRate,
+        PitchRcRate,
+        RollRcExpo,
+        PitchRcExpo,
+    ]
+};
+
+/// `adjustmentFunction_e` in Betaflight 4.x
+const BETAFLIGHT_ADJUSTMENTS: &[AdjustmentFunction] = {
+    use AdjustmentFunction::*;
+    &[
+        RcRate,
+        RcExpo,
+        ThrottleExpo,
+        PitchRollRate,
+        YawRate,
+        PitchRollP,
+        PitchRollI,
+        PitchRollD,
+        YawP,
+        YawI,
+        YawD,
+        RateProfile,
+        PitchRate,
+        RollRate,
+        PitchP,
+        PitchI,
+        PitchD,
+        RollP,
+        RollI,
+        RollD,
+        RcRateYaw,
+        PitchRollF,
+        FeedforwardTransition,
+        HorizonStrength,
+        RollRcRate,
+        PitchRcRate,
+        RollRcExpo,
+        PitchRcExpo,
+        PidAudio,
+        PitchF,
+        RollF,
+        YawF,
+        OsdProfile,
+        LedProfile,
+        LedDimmer,
+    ]
+};
+
+/// `adjustmentFunction_e` in INAV
+const INAV_ADJUSTMENTS: &[AdjustmentFunction] = {
+    use AdjustmentFunction::*;
+    &[
+        RcExpo,
+        RcYawExpo,
+        ManualRcExpo,
+        ManualRcYawExpo,
+        ThrottleExpo,
+        PitchRollRate,
+        PitchRate,
+        RollRate,
+        YawRate,
+        ManualPitchRollRate,
+        ManualPitchRate,
+        ManualRollRate,
+        ManualYawRate,
+        PitchRollP,
+        PitchRollI,
+        PitchRollD,
+        PitchRollF,
+        PitchP,
+        PitchI,
+        PitchD,
+        PitchF,
+        RollP,
+        RollI,
+        RollD,
+        RollF,
+        YawP,
+        YawI,
+        YawD,
+        YawF,
+        RateProfile,
+        PitchBoardAlignment,
+        RollBoardAlignment,
+        LevelP,
+        LevelI,
+        LevelD,
+        PosXyP,
+        PosXyI,
+        PosXyD,
+        PosZP,
+        PosZI,
+        PosZD,
+        HeadingP,
+        VelXyP,
+        VelXyI,
+        VelXyD,
+        VelZP,
+        VelZI,
+        VelZD,
+        FwMinThrottleDownPitchAngle,
+        VtxPowerLevel,
+        Tpa,
+        TpaBreakpoint,
+        NavFwControlSmoothness,
+        FwTpaTimeConstant,
+        FwLevelTrim,
+        NavWpMultiMissionIndex,
+    ]
+};
+
+impl fmt::Display for AdjustmentFunction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::RcRate => "RC rate",
+            Self::RcExpo => "RC expo",
+            Self::RcYawExpo => "RC yaw expo",
+            Self::ThrottleExpo => "throttle expo",
+            Self::PitchRollRate => "pitch & roll rate",
+            Self::YawRate => "yaw rate",
+            Self::PitchRollP => "pitch & roll P",
+            Self::PitchRollI => "pitch & roll I",
+            Self::PitchRollD => "pitch & roll D",
+            Self::PitchRollF => "pitch & roll F",
+            Self::DSetpoint => "D setpoint weight",
+            Self::DSetpointTransition => "D setpoint transition",
+            Self::YawP => "yaw P",
+            Self::YawI => "yaw I",
+            Self::YawD => "yaw D",
+            Self::YawF => "yaw F",
+            Self::RateProfile => "rate profile",
+            Self::PitchRate => "pitch rate",
+            Self::RollRate => "roll rate",
+            Self::PitchP => "pitch P",
+            Self::PitchI => "pitch I",
+            Self::PitchD => "pitch D",
+            Self::PitchF => "pitch F",
+            Self::RollP => "roll P",
+            Self::RollI => "roll I",
+            Self::RollD => "roll D",
+            Self::RollF => "roll F",
+            Self::RcRateYaw => "yaw RC rate",
+            Self::FeedforwardTransition => "feedforward transition",
+            Self::HorizonStrength => "horizon strength",
+            Self::RollRcRate => "roll RC rate",
+            Self::PitchRcRate => "pitch RC rate",
+            Self::RollRcExpo => "roll RC expo",
+            Self::PitchRcExpo => "pitch RC expo",
+            Self::PidAudio => "PID audio",
+            Self::OsdProfile => "OSD profile",
+            Self::LedProfile => "LED profile",
+            Self::LedDimmer => "LED dimmer",
+            Self::ManualRcExpo => "manual RC expo",
+            Self::ManualRcYawExpo => "manual RC yaw expo",
+            Self::ManualPitchRollRate => "manual pitch & roll rate",
+            Self::ManualPitchRate => "manual pitch rate",
+            Self::ManualRollRate => "manual roll rate",
+            Self::ManualYawRate => "manual yaw rate",
+            Self::PitchBoardAlignment => "pitch board alignment",
+            Self::RollBoardAlignment => "roll board alignment",
+            Self::LevelP => "level P",
+            Self::LevelI => "level I",
+            Self::LevelD => "level D",
+            Self::PosXyP => "position XY P",
+            Self::PosXyI => "position XY I",
+            Self::PosXyD => "position XY D",
+            Self::PosZP => "position Z P",
+            Self::PosZI => "position Z I",
+            Self::PosZD => "position Z D",
+            Self::HeadingP => "heading P",
+            Self::VelXyP => "velocity XY P",
+            Self::VelXyI => "velocity XY I",
+            Self::VelXyD => "velocity XY D",
+            Self::VelZP => "velocity Z P",
+            Self::VelZI => "velocity Z I",
+            Self::VelZD => "velocity Z D",
+            Self::FwMinThrottleDownPitchAngle => "fixed wing min throttle down pitch angle",
+            Self::VtxPowerLevel => "VTX power level",
+            Self::Tpa => "TPA",
+            Self::TpaBreakpoint => "TPA breakpoint",
+            Self::NavFwControlSmoothness => "fixed wing navigation control smoothness",
+            Self::FwTpaTimeConstant => "fixed wing TPA time constant",
+            Self::FwLevelTrim => "fixed wing level trim",
+            Self::NavWpMultiMissionIndex => "multi-mission index",
+            Self::Unknown(raw) => return write!(f, "unknown adjustment ({raw})"),
+        };
+
+        f.write_str(name)
+    }
+}
+
 impl Event {
     #[instrument(level = "debug", name = "Event::parse", skip_all, fields(kind))]
-    pub(crate) fn parse(data: &mut Reader) -> InternalResult<Self> {
+    pub(crate) fn parse(
+        data: &mut Reader,
+        firmware: FirmwareKind,
+        version: Option<&FirmwareVersion>,
+        last_time: Option<u64>,
+    ) -> InternalResult<Self> {
         let byte = data.read_u8().ok_or(InternalError::Eof)?;
         let kind = EventKind::from_byte(byte).ok_or_else(|| {
             tracing::debug!("found invalid event: {byte:0>#2x}");
@@ -51,10 +451,60 @@ pub(crate) fn parse(data: &mut Reader) -> InternalResult<Self> {
 
         let event = match kind {
             EventKind::SyncBeep => {
-                // TODO: SyncBeep handle time rollover
-
                 let time = decode::variable(data)?;
-                Self::SyncBeep(time.into())
+                Self::SyncBeep(expand_time(time, last_time))
+            }
+
+            EventKind::AutotuneCycleStart => {
+                let phase = read_u8(data)?;
+                let cycle = read_u8(data)?;
+                let [p, i, d] = [read_u8(data)?, read_u8(data)?, read_u8(data)?];
+
+                Self::AutotuneCycleStart {
+                    phase,
+                    cycle: cycle & 0x7F,
+                    rising: cycle & 0x80 != 0,
+                    p,
+                    i,
+                    d,
+                }
+            }
+
+            EventKind::AutotuneCycleResult => {
+                let flags = read_u8(data)?;
+                let [p, i, d] = [read_u8(data)?, read_u8(data)?, read_u8(data)?];
+
+                Self::AutotuneCycleResult {
+                    overshot: flags & 1 != 0,
+                    timed_out: flags & 2 != 0,
+                    p,
+                    i,
+                    d,
+                }
+            }
+
+            EventKind::AutotuneTargets => {
+                let eof = || InternalError::Eof;
+
+                Self::AutotuneTargets {
+                    current_angle: data.read_i16().ok_or_else(eof)?,
+                    target_angle: data.read_i8().ok_or_else(eof)?,
+                    target_angle_at_peak: data.read_i8().ok_or_else(eof)?,
+                    first_peak_angle: data.read_i16().ok_or_else(eof)?,
+                    second_peak_angle: data.read_i16().ok_or_else(eof)?,
+                }
+            }
+
+            EventKind::GtuneResult => {
+                let axis = read_u8(data)?;
+                let gyro_average = decode::variable_signed(data)?;
+                let new_p = data.read_i16().ok_or(InternalError::Eof)?;
+
+                Self::GtuneResult {
+                    axis,
+                    gyro_average,
+                    new_p,
+                }
             }
 
             EventKind::InflightAdjustment => {
@@ -67,14 +517,14 @@ pub(crate) fn parse(data: &mut Reader) -> InternalResult<Self> {
                 };
 
                 Self::InflightAdjustment {
-                    function: function & 0x7F,
+                    function: AdjustmentFunction::new(function & 0x7F, firmware, version),
                     new_value,
                 }
             }
 
             EventKind::Resume => {
                 let log_iteration = decode::variable(data)?;
-                let time = decode::variable(data)?;
+                let time = expand_time(decode::variable(data)?, last_time);
 
                 Self::Resume {
                     log_iteration,
@@ -84,12 +534,13 @@ pub(crate) fn parse(data: &mut Reader) -> InternalResult<Self> {
 
             EventKind::Disarm => {
                 let reason = decode::variable(data)?;
-                Self::Disarm(reason)
+                Self::Disarm(DisarmReason::new(reason, firmware))
             }
 
             EventKind::FlightMode => {
-                let flags = decode::variable(data)?;
-                let last_flags = decode::variable(data)?;
+                let layout = FlagLayout::new(firmware, version);
+                let flags = FlightModeSet::new(decode::variable(data)?, layout);
+                let last_flags = FlightModeSet::new(decode::variable(data)?, layout);
                 Self::FlightMode { flags, last_flags }
             }
 
@@ -109,18 +560,14 @@ pub(crate) fn parse(data: &mut Reader) -> InternalResult<Self> {
 
                     let reason = data.read_u8().ok_or(InternalError::Eof)?.into();
 
-                    if data.read_u8() != Some(b')') {
-                        return Err(InternalError::Retry);
-                    }
+                    check_message(data, b")")?;
 
-                    Some(reason)
+                    Some(DisarmReason::new(reason, firmware))
                 } else {
                     None
                 };
 
-                if data.read_u8() != Some(0) {
-                    return Err(InternalError::Retry);
-                }
+                check_message(data, b"\0")?;
 
                 Self::End { disarm_reason }
             }
@@ -128,6 +575,164 @@ pub(crate) fn parse(data: &mut Reader) -> InternalResult<Self> {
 
         Ok(event)
     }
+
+    pub(crate) fn encode(
+        &self,
+        out: &mut Vec<u8>,
+        firmware: FirmwareKind,
+        version: Option<&FirmwareVersion>,
+        last_time: Option<u64>,
+    ) -> WriteResult<()> {
+        let unencodable = |field: &str| WriteError::Unencodable {
+            frame: FrameKind::Event,
+            field: field.to_owned(),
+        };
+        let encode_time = |time: u64| {
+            let truncated = truncate_time(time);
+            if expand_time(truncated, last_time) == time {
+                Ok(truncated)
+            } else {
+                Err(unencodable("time"))
+            }
+        };
+
+        match *self {
+            Self::SyncBeep(time) => {
+                let time = encode_time(time)?;
+
+                out.push(EventKind::SyncBeep.into());
+                encode::variable(out, time);
+            }
+
+            Self::AutotuneCycleStart {
+                phase,
+                cycle,
+                rising,
+                p,
+                i,
+                d,
+            } => {
+                if cycle & 0x80 != 0 {
+                    return Err(unencodable("cycle"));
+                }
+
+                out.push(EventKind::AutotuneCycleStart.into());
+                out.extend_from_slice(&[phase, cycle | (u8::from(rising) << 7), p, i, d]);
+            }
+
+            Self::AutotuneCycleResult {
+                overshot,
+                timed_out,
+                p,
+                i,
+                d,
+            } => {
+                let flags = u8::from(overshot) | (u8::from(timed_out) << 1);
+
+                out.push(EventKind::AutotuneCycleResult.into());
+                out.extend_from_slice(&[flags, p, i, d]);
+            }
+
+            Self::AutotuneTargets {
+                current_angle,
+                target_angle,
+                target_angle_at_peak,
+                first_peak_angle,
+                second_peak_angle,
+            } => {
+                out.push(EventKind::AutotuneTargets.into());
+                out.extend_from_slice(&current_angle.to_le_bytes());
+                out.extend_from_slice(&target_angle.to_le_bytes());
+                out.extend_from_slice(&target_angle_at_peak.to_le_bytes());
+                out.extend_from_slice(&first_peak_angle.to_le_bytes());
+                out.extend_from_slice(&second_peak_angle.to_le_bytes());
+            }
+
+            Self::GtuneResult {
+                axis,
+                gyro_average,
+                new_p,
+            } => {
+                out.push(EventKind::GtuneResult.into());
+                out.push(axis);
+                encode::variable_signed(out, gyro_average);
+                out.extend_from_slice(&new_p.to_le_bytes());
+            }
+
+            Self::InflightAdjustment {
+                function,
+                new_value,
+            } => {
+                let function = match function.to_raw(firmware, version) {
+                    Some(function) if function & 0x80 == 0 => function,
+                    _ => return Err(unencodable("function")),
+                };
+
+                out.push(EventKind::InflightAdjustment.into());
+                match new_value {
+                    AdjustedValue::Float(value) => {
+                        out.push(function | 0x80);
+                        out.extend_from_slice(&value.to_bits().to_le_bytes());
+                    }
+                    AdjustedValue::Int(value) => {
+                        out.push(function);
+                        encode::variable_signed(out, value);
+                    }
+                }
+            }
+
+            Self::Resume {
+                log_iteration,
+                time,
+            } => {
+                let time = encode_time(time)?;
+
+                out.push(EventKind::Resume.into());
+                encode::variable(out, log_iteration);
+                encode::variable(out, time);
+            }
+
+            Self::Disarm(reason) => {
+                let reason = reason
+                    .to_raw(firmware)
+                    .ok_or_else(|| unencodable("disarm_reason"))?;
+
+                out.push(EventKind::Disarm.into());
+                encode::variable(out, reason);
+            }
+
+            Self::FlightMode { flags, last_flags } => {
+                out.push(EventKind::FlightMode.into());
+                encode::variable(out, flags.to_raw());
+                encode::variable(out, last_flags.to_raw());
+            }
+
+            Self::ImuFailure { error } => {
+                out.push(EventKind::ImuFailure.into());
+                encode::variable(out, error);
+            }
+
+            Self::End { disarm_reason } => {
+                out.push(EventKind::End.into());
+                out.extend_from_slice(b"End of log");
+
+                if let Some(reason) = disarm_reason {
+                    let reason = reason
+                        .to_raw(firmware)
+                        .and_then(|reason| u8::try_from(reason).ok())
+                        .ok_or_else(|| unencodable("disarm_reason"))?;
+
+                    out.extend_from_slice(b" (disarm reason:");
+                    out.push(reason);
+                    out.push(b')');
+                }
+
+                out.push(0);
+            }
+        }
+
+        Ok(())
+    }
 }
 
 byte_enum! {
@@ -135,15 +740,23 @@ pub(crate) fn parse(data: &mut Reader) -> InternalResult<Self> {
     #[repr(u8)]
     pub(crate) enum EventKind {
         SyncBeep = 0,
+        AutotuneCycleStart = 10,
+        AutotuneCycleResult = 11,
+        AutotuneTargets = 12,
         InflightAdjustment = 13,
         Resume = 14,
         Disarm = 15,
+        GtuneResult = 20,
         FlightMode = 30,
         ImuFailure = 40,
         End = 255,
     }
 }
 
+fn read_u8(data: &mut Reader) -> InternalResult<u8> {
+    data.read_u8().ok_or(InternalError::Eof)
+}
+
 fn check_message(bytes: &mut Reader, message: &[u8]) -> InternalResult<()> {
     let bytes = bytes.read_n_bytes(message.len());
 
@@ -157,3 +770,106 @@ fn check_message(bytes: &mut Reader, message: &[u8]) -> InternalResult<()> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use alloc::format;
+
+    use test_case::case;
+
+    use super::*;
+
+    #[case(&[10, 2, 0x83, 40, 30, 20], Event::AutotuneCycleStart {
+        phase: 2,
+        cycle: 3,
+        rising: true,
+        p: 40,
+        i: 30,
+        d: 20,
+    }; "autotune cycle start")]
+    #[case(&[11, 0b10, 40, 30, 20], Event::AutotuneCycleResult {
+        overshot: false,
+        timed_out: true,
+        p: 40,
+        i: 30,
+        d: 20,
+    }; "autotune cycle result")]
+    #[case(&[12, 0x2C, 0x01, 0xE2, 25, 0x90, 0x01, 0xFF, 0xFF], Event::AutotuneTargets {
+        current_angle: 300,
+        target_angle: -30,
+        target_angle_at_peak: 25,
+        first_peak_angle: 400,
+        second_peak_angle: -1,
+    }; "autotune targets")]
+    #[case(&[20, 1, 0x03, 0x2D, 0x00], Event::GtuneResult {
+        axis: 1,
+        gyro_average: -2,
+        new_p: 45,
+    }; "gtune result")]
+    #[case(&[30, 0x81, 0x80, 0x80, 0x80, 0x08, 0x01], Event::FlightMode {
+        flags: FlightModeSet::new((1 << 31) | 1, FlagLayout::Betaflight),
+        last_flags: FlightModeSet::new(1, FlagLayout::Betaflight),
+    }; "flight mode")]
+    fn parse(bytes: &[u8], expected: Event) {
+        let mut data = Reader::new(bytes);
+        let parsed = Event::parse(&mut data, FirmwareKind::Betaflight, None, None).unwrap();
+        assert_eq!(expected, parsed);
+        assert!(data.is_empty());
+
+        let mut out = Vec::new();
+        expected
+            .encode(&mut out, FirmwareKind::Betaflight, None, None)
+            .unwrap();
+        assert_eq!(bytes, out);
+    }
+
+    #[case(FirmwareKind::Betaflight, None, 1, AdjustmentFunction::RcRate; "betaflight first")]
+    #[case(FirmwareKind::Betaflight, None, 22, AdjustmentFunction::PitchRollF; "betaflight 4")]
+    #[case(FirmwareKind::Betaflight, None, 35, AdjustmentFunction::LedDimmer; "betaflight last")]
+    #[case(FirmwareKind::Betaflight, None, 36, AdjustmentFunction::Unknown(36); "betaflight unknown")]
+    #[case(FirmwareKind::Betaflight, Some("3.5.7"), 22, AdjustmentFunction::DSetpoint; "betaflight 3")]
+    #[case(FirmwareKind::Betaflight, Some("3.5.7"), 28, AdjustmentFunction::PitchRcExpo; "betaflight 3 last")]
+    #[case(FirmwareKind::Betaflight, Some("3.5.7"), 30, AdjustmentFunction::Unknown(30); "betaflight 3 unknown")]
+    #[case(FirmwareKind::Betaflight, Some("4.0.0"), 23, AdjustmentFunction::FeedforwardTransition; "betaflight 4.0")]
+    #[case(FirmwareKind::EmuFlight, Some("0.4.1"), 21, AdjustmentFunction::RcRateYaw; "emuflight")]
+    #[case(FirmwareKind::EmuFlight, Some("0.4.1"), 22, AdjustmentFunction::Unknown(22); "emuflight unknown")]
+    #[case(FirmwareKind::Cleanflight, Some("2.5.0"), 20, AdjustmentFunction::RollD; "cleanflight")]
+    #[case(FirmwareKind::Rotorflight, Some("4.3.0"), 1, AdjustmentFunction::Unknown(1); "rotorflight")]
+    #[case(FirmwareKind::Inav, None, 1, AdjustmentFunction::RcExpo; "inav first")]
+    #[case(FirmwareKind::Inav, None, 56, AdjustmentFunction::NavWpMultiMissionIndex; "inav last")]
+    #[case(FirmwareKind::Inav, None, 0, AdjustmentFunction::Unknown(0); "none")]
+    fn adjustment_function(
+        firmware: FirmwareKind,
+        version: Option<&str>,
+        raw: u8,
+        expected: AdjustmentFunction,
+    ) {
+        let revision = version.map(|version| format!("{firmware:?} {version} (1234567) STM32F7X2"));
+        let version = revision
+            .as_deref()
+            .map(|revision| FirmwareVersion::parse(revision, None).unwrap());
+
+        let function = AdjustmentFunction::new(raw, firmware, version.as_ref());
+        assert_eq!(expected, function);
+        assert_eq!(Some(raw), function.to_raw(firmware, version.as_ref()));
+    }
+
+    #[case(AdjustmentFunction::PitchRollF => "pitch & roll F")]
+    #[case(AdjustmentFunction::Unknown(36) => "unknown adjustment (36)")]
+    fn adjustment_function_display(function: AdjustmentFunction) -> alloc::string::String {
+        alloc::string::ToString::to_string(&function)
+    }
+
+    #[test]
+    fn unencodable_adjustment_function() {
+        let event = Event::InflightAdjustment {
+            function: AdjustmentFunction::LedDimmer,
+            new_value: AdjustedValue::Int(1),
+        };
+
+        let mut out = Vec::new();
+        assert!(event
+            .encode(&mut out, FirmwareKind::Inav, None, None)
+            .is_err());
+    }
+}
@@ -46,7 +46,7 @@ fn from_iter<T: IntoIterator<Item = S>>(iter: T) -> Self {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub(crate) struct AppliedFilter(Vec<usize>);
 
 impl AppliedFilter {
@@ -1,23 +1,30 @@
 use alloc::borrow::ToOwned;
+use alloc::sync::Arc;
 use alloc::vec::Vec;
 use core::iter;
 
 use tracing::instrument;
 
-use super::{read_field_values, DataFrameKind, DataFrameProperty, FieldDef, GpsHomeFrame, Unit};
+use super::{
+    read_field_values, DataFrameKind, DataFrameProperty, FieldDef, GpsHomeFrame, GpsPosition, Unit,
+};
+use crate::data::MainFrameHistory;
 use crate::filter::{AppliedFilter, FieldFilter};
-use crate::parser::{decode, to_base_field, Encoding, InternalResult};
+use crate::parser::{
+    decode, encode, expand_time, to_base_field, truncate_time, Encoding, InternalResult,
+};
 use crate::predictor::{Predictor, PredictorContext};
 use crate::units::prelude::*;
-use crate::units::FromRaw;
+use crate::units::{FromRaw, UnitContext};
 use crate::utils::as_i32;
+use crate::writer::WriteResult;
 use crate::{Headers, HeadersParseError, HeadersParseResult, Reader};
 
 /// Data parsed from a GPS frame.
 #[derive(Debug, Clone)]
 pub struct GpsFrame<'data, 'headers> {
     headers: &'headers Headers<'data>,
-    raw: RawGpsFrame,
+    pub(crate) raw: RawGpsFrame,
 }
 
 impl super::seal::Sealed for GpsFrame<'_, '_> {}
@@ -26,24 +33,132 @@ impl super::Frame for GpsFrame<'_, '_> {
     type Value = GpsValue;
 
     fn get(&self, index: usize) -> Option<Self::Value> {
+        let def = self.headers.gps_frame_def.as_ref().unwrap();
+        let field = |i: usize| (def.fields[i].unit, def.fields[i].signed);
+
+        self.raw
+            .get(index, &def.filter, field, &UnitContext::new(self.headers))
+    }
+}
+
+impl<'data, 'headers> GpsFrame<'data, 'headers> {
+    pub(crate) fn new(headers: &'headers Headers<'data>, raw: RawGpsFrame) -> Self {
+        Self { headers, raw }
+    }
+
+    /// Returns the GPS home position used to predict this frame's
+    /// coordinates, if the log had one before this frame.
+    pub fn home(&self) -> Option<GpsPosition> {
+        self.raw.home
+    }
+
+    /// Converts this frame into an [`OwnedGpsFrame`] that can outlive the
+    /// [`Headers`].
+    ///
+    /// Returns `None` if `def` does not match the [`Headers`] of this frame,
+    /// eg if it was created for another log or before applying a filter.
+    pub fn into_owned(self, def: &Arc<OwnedGpsFrameDef>) -> Option<OwnedGpsFrame> {
+        def.matches(self.headers).then(|| OwnedGpsFrame {
+            def: Arc::clone(def),
+            raw: self.raw,
+        })
+    }
+}
+
+/// An owned version of [`GpsFrame`], created by [`GpsFrame::into_owned`].
+#[derive(Debug, Clone)]
+pub struct OwnedGpsFrame {
+    def: Arc<OwnedGpsFrameDef>,
+    raw: RawGpsFrame,
+}
+
+impl super::seal::Sealed for OwnedGpsFrame {}
+
+impl OwnedGpsFrame {
+    /// See [`GpsFrame::home`].
+    pub fn home(&self) -> Option<GpsPosition> {
+        self.raw.home
+    }
+}
+
+impl super::Frame for OwnedGpsFrame {
+    type Value = GpsValue;
+
+    fn get(&self, index: usize) -> Option<Self::Value> {
+        let def = &*self.def;
+        self.raw
+            .get(index, &def.filter, |i| def.fields[i], &def.units)
+    }
+}
+
+/// The parts of a [`GpsFrameDef`] and its [`Headers`] needed by
+/// [`OwnedGpsFrame`]s.
+///
+/// This includes the filter applied at the time it was created.
+#[derive(Debug, Clone)]
+pub struct OwnedGpsFrameDef {
+    /// Unit and signedness of each field, excluding `time`
+    fields: Vec<(GpsUnit, bool)>,
+    filter: AppliedFilter,
+    units: UnitContext,
+}
+
+impl OwnedGpsFrameDef {
+    /// Returns `None` if the log does not have a GPS frame definition.
+    pub fn new(headers: &Headers) -> Option<Self> {
+        let def = headers.gps_frame_def.as_ref()?;
+
+        Some(Self {
+            fields: def.fields.iter().map(|f| (f.unit, f.signed)).collect(),
+            filter: def.filter.clone(),
+            units: UnitContext::new(headers),
+        })
+    }
+
+    fn matches(&self, headers: &Headers) -> bool {
+        headers.gps_frame_def.as_ref().map_or(false, |def| {
+            self.fields
+                .iter()
+                .copied()
+                .eq(def.fields.iter().map(|f| (f.unit, f.signed)))
+                && self.filter == def.filter
+                && self.units == UnitContext::new(headers)
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct RawGpsFrame {
+    pub(crate) time: u64,
+    pub(crate) values: Vec<u32>,
+    pub(crate) home: Option<GpsPosition>,
+}
+
+impl RawGpsFrame {
+    fn get(
+        &self,
+        index: usize,
+        filter: &AppliedFilter,
+        field: impl Fn(usize) -> (GpsUnit, bool),
+        units: &UnitContext,
+    ) -> Option<GpsValue> {
         let value = if index == 0 {
-            GpsValue::FrameTime(Time::from_raw(self.raw.time, self.headers))
+            GpsValue::FrameTime(Time::from_raw(self.time, units))
         } else {
-            let def = self.headers.gps_frame_def.as_ref().unwrap();
-            let index = def.filter.get(index - 1)?;
-            let def = &def.fields[index];
-            let raw = self.raw.values[index];
+            let index = filter.get(index - 1)?;
+            let (unit, signed) = field(index);
+            let raw = self.values[index];
 
-            match def.unit {
+            match unit {
                 GpsUnit::FrameTime => unreachable!(),
                 GpsUnit::Coordinate => {
-                    assert!(def.signed);
+                    assert!(signed);
                     let value = as_i32(raw);
 
                     GpsValue::Coordinate(f64::from(value) / 10000000.)
                 }
                 GpsUnit::Altitude => {
-                    let altitude = if def.signed {
+                    let altitude = if signed {
                         as_i32(raw).into()
                     } else {
                         raw.into()
@@ -52,31 +167,36 @@ fn get(&self, index: usize) -> Option<Self::Value> {
                     GpsValue::Altitude(Length::new::<meter>(altitude))
                 }
                 GpsUnit::Velocity => {
-                    assert!(!def.signed);
-                    GpsValue::Velocity(Velocity::from_raw(raw, self.headers))
+                    assert!(!signed);
+                    GpsValue::Velocity(Velocity::from_raw(raw, units))
                 }
                 GpsUnit::Heading => {
-                    assert!(!def.signed);
+                    assert!(!signed);
                     GpsValue::Heading(f64::from(raw) / 10.)
                 }
-                GpsUnit::Unitless => GpsValue::new_unitless(raw, def.signed),
+                GpsUnit::Unitless => GpsValue::new_unitless(raw, signed),
             }
         };
 
         Some(value)
     }
-}
 
-impl<'data, 'headers> GpsFrame<'data, 'headers> {
-    pub(crate) fn new(headers: &'headers Headers<'data>, raw: RawGpsFrame) -> Self {
-        Self { headers, raw }
-    }
-}
+    /// Adds the `home` coordinates to any fields that were predicted without a
+    /// GPS home frame.
+    #[cfg(feature = "rayon")]
+    pub(crate) fn apply_home(&mut self, def: &GpsFrameDef, home: GpsPosition) {
+        for (value, field) in self.values.iter_mut().zip(&def.fields) {
+            let home = match field.predictor {
+                Predictor::HomeLat => home.latitude,
+                Predictor::HomeLon => home.longitude,
+                _ => continue,
+            };
+
+            *value = value.wrapping_add(crate::utils::as_u32(home));
+        }
 
-#[derive(Debug, Clone)]
-pub(crate) struct RawGpsFrame {
-    pub(crate) time: u64,
-    pub(crate) values: Vec<u32>,
+        self.home = Some(home);
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -195,21 +315,21 @@ pub(crate) fn parse(
         &self,
         data: &mut Reader,
         headers: &Headers,
-        last_main_time: Option<u64>,
+        history: &MainFrameHistory,
         last_home: Option<&GpsHomeFrame>,
     ) -> InternalResult<RawGpsFrame> {
+        let last_main_time = history.last().map(|frame| frame.time);
+        let ctx = PredictorContext::with_gps(headers, last_home.map(|home| home.0), last_main_time);
+
         let time = {
-            let time = last_main_time.unwrap_or(0);
-            let offset = decode::variable(data)?.into();
+            let offset = decode::variable(data)?;
+            let time = self.time.predictor.apply(offset, false, None, &ctx);
 
-            let time = time.saturating_add(offset);
             tracing::trace!(time, offset);
-            time
+            expand_time(time, history.last_time())
         };
 
-        let raw = read_field_values(data, &self.fields, |f| f.encoding)?;
-
-        let ctx = PredictorContext::with_home(headers, last_home.map(|home| home.0));
+        let raw = read_field_values(data, headers.version, &self.fields, |f| f.encoding)?;
         let mut values = Vec::with_capacity(raw.len());
 
         for (i, field) in self.fields.iter().enumerate() {
@@ -230,7 +350,42 @@ pub(crate) fn parse(
             values.push(value);
         }
 
-        Ok(RawGpsFrame { time, values })
+        Ok(RawGpsFrame {
+            time,
+            values,
+            home: last_home.map(|home| home.0),
+        })
+    }
+
+    pub(crate) fn encode(
+        &self,
+        out: &mut Vec<u8>,
+        headers: &Headers,
+        history: &MainFrameHistory,
+        last_home: Option<&GpsHomeFrame>,
+        frame: &RawGpsFrame,
+    ) -> WriteResult<()> {
+        let kind = DataFrameKind::Gps;
+        super::check_field_count(kind, self.fields.len(), frame.values.len())?;
+
+        let last_main_time = history.last().map(|frame| frame.time);
+        let ctx = PredictorContext::with_gps(headers, last_home.map(|home| home.0), last_main_time);
+
+        let time = truncate_time(frame.time);
+        if expand_time(time, history.last_time()) != frame.time {
+            return Err(super::unencodable_error(kind, self.time.name));
+        }
+        encode::variable(out, self.time.predictor.unapply(time, false, None, &ctx));
+
+        let raw = (self.fields.iter().zip(&frame.values))
+            .map(|(field, &value)| {
+                let signed = field.encoding.is_signed();
+                field.predictor.unapply(value, signed, None, &ctx)
+            })
+            .collect::<Vec<_>>();
+
+        super::write_field_values(out, headers.version, &self.fields, |f| f.encoding, &raw)
+            .map_err(|i| super::unencodable_error(kind, self.fields[i].name))
     }
 }
 
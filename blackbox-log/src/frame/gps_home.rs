@@ -6,16 +6,20 @@
 use super::{read_field_values, DataFrameKind, DataFrameProperty};
 use crate::parser::{Encoding, InternalResult};
 use crate::predictor::{Predictor, PredictorContext};
-use crate::utils::as_i32;
+use crate::utils::{as_i32, as_u32};
+use crate::writer::WriteResult;
 use crate::{Headers, HeadersParseError, HeadersParseResult, Reader};
 
 #[derive(Debug, Clone)]
 pub(crate) struct GpsHomeFrame(pub(crate) GpsPosition);
 
+/// A raw GPS position, as logged in GPS home frames.
 #[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
-pub(crate) struct GpsPosition {
-    pub(crate) latitude: i32,
-    pub(crate) longitude: i32,
+pub struct GpsPosition {
+    /// Latitude in degrees, multiplied by 10,000,000
+    pub latitude: i32,
+    /// Longitude in degrees, multiplied by 10,000,000
+    pub longitude: i32,
 }
 
 #[derive(Debug, Clone)]
@@ -47,8 +51,8 @@ pub(crate) fn parse(
         data: &mut Reader,
         headers: &Headers,
     ) -> InternalResult<GpsHomeFrame> {
-        let raw = read_field_values(data, &self.0, |f| f.encoding)?;
-        let _ = read_field_values(data, &self.1, |&f| f)?;
+        // Any extra fields are read in the same pass to share Elias delta bits
+        let raw = read_field_values(data, headers.version, &self.encodings(), |&f| f)?;
 
         let ctx = PredictorContext::new(headers);
         let values = raw
@@ -77,6 +81,38 @@ pub(crate) fn parse(
             longitude,
         }))
     }
+
+    pub(crate) fn encode(
+        &self,
+        out: &mut Vec<u8>,
+        headers: &Headers,
+        position: GpsPosition,
+    ) -> WriteResult<()> {
+        let ctx = PredictorContext::new(headers);
+        let mut raw = [position.latitude, position.longitude]
+            .iter()
+            .zip(self.0.iter())
+            .map(|(&value, field)| field.predictor.unapply(as_u32(value), true, None, &ctx))
+            .collect::<Vec<_>>();
+
+        // Any extra fields are not exposed, so write them as zero
+        raw.resize(self.0.len() + self.1.len(), 0);
+
+        super::write_field_values(out, headers.version, &self.encodings(), |&f| f, &raw).map_err(
+            |i| {
+                let name = self.0.get(i).map_or("GPS_home", |field| field.name);
+                super::unencodable_error(DataFrameKind::GpsHome, name)
+            },
+        )
+    }
+
+    fn encodings(&self) -> Vec<Encoding> {
+        self.0
+            .iter()
+            .map(|field| field.encoding)
+            .chain(self.1.iter().copied())
+            .collect()
+    }
 }
 
 #[allow(dead_code)]
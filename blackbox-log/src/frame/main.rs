@@ -1,4 +1,5 @@
 use alloc::borrow::ToOwned;
+use alloc::sync::Arc;
 use alloc::vec::Vec;
 use core::iter;
 
@@ -7,18 +8,24 @@
 use super::{read_field_values, DataFrameKind, DataFrameProperty, FieldDef, FrameKind, Unit};
 use crate::data::MainFrameHistory;
 use crate::filter::{AppliedFilter, FieldFilter};
-use crate::parser::{decode, to_base_field, Encoding, InternalResult};
+use crate::headers::{DebugMode, InterInterval};
+use crate::parser::{
+    decode, encode, expand_time, to_base_field, truncate_time, Encoding, InternalResult,
+};
 use crate::predictor::{self, Predictor, PredictorContext};
 use crate::units::prelude::*;
-use crate::units::FromRaw;
+use crate::units::{
+    new_erpm, new_motor_output, new_rc_command, new_rssi, new_setpoint, FromRaw, UnitContext,
+};
 use crate::utils::as_i32;
+use crate::writer::WriteResult;
 use crate::{Headers, HeadersParseError, HeadersParseResult, Reader};
 
 /// Data parsed from a main frame.
 #[derive(Debug)]
 pub struct MainFrame<'data, 'headers, 'parser> {
     headers: &'headers Headers<'data>,
-    raw: &'parser RawMainFrame,
+    pub(crate) raw: &'parser RawMainFrame,
 }
 
 impl super::seal::Sealed for MainFrame<'_, '_, '_> {}
@@ -27,34 +34,149 @@ impl super::Frame for MainFrame<'_, '_, '_> {
     type Value = MainValue;
 
     fn get(&self, index: usize) -> Option<MainValue> {
+        let def = &self.headers.main_frame_def;
+        let field = |i: usize| def.fields[i].field_unit();
+
+        self.raw
+            .get(index, &def.filter, field, &UnitContext::new(self.headers))
+    }
+}
+
+impl<'data, 'headers, 'parser> MainFrame<'data, 'headers, 'parser> {
+    pub(crate) fn new(headers: &'headers Headers<'data>, raw: &'parser RawMainFrame) -> Self {
+        Self { headers, raw }
+    }
+
+    /// Copies this frame into an [`OwnedMainFrame`] that can outlive the
+    /// parser and [`Headers`].
+    ///
+    /// Returns `None` if `def` does not match the [`Headers`] of this frame,
+    /// eg if it was created for another log or before applying a filter.
+    pub fn into_owned(self, def: &Arc<OwnedMainFrameDef>) -> Option<OwnedMainFrame> {
+        def.matches(self.headers).then(|| OwnedMainFrame {
+            def: Arc::clone(def),
+            raw: self.raw.clone(),
+        })
+    }
+}
+
+/// An owned version of [`MainFrame`], created by [`MainFrame::into_owned`].
+#[derive(Debug, Clone)]
+pub struct OwnedMainFrame {
+    def: Arc<OwnedMainFrameDef>,
+    raw: RawMainFrame,
+}
+
+impl super::seal::Sealed for OwnedMainFrame {}
+
+impl super::Frame for OwnedMainFrame {
+    type Value = MainValue;
+
+    fn get(&self, index: usize) -> Option<MainValue> {
+        let def = &*self.def;
+        self.raw
+            .get(index, &def.filter, |i| def.fields[i], &def.units)
+    }
+}
+
+/// The parts of a [`MainFrameDef`] and its [`Headers`] needed by
+/// [`OwnedMainFrame`]s.
+///
+/// This includes the filter applied at the time it was created.
+#[derive(Debug, Clone)]
+pub struct OwnedMainFrameDef {
+    fields: Vec<FieldUnit>,
+    filter: AppliedFilter,
+    units: UnitContext,
+}
+
+impl OwnedMainFrameDef {
+    pub fn new(headers: &Headers) -> Self {
+        let def = &headers.main_frame_def;
+
+        Self {
+            fields: def.fields.iter().map(MainFieldDef::field_unit).collect(),
+            filter: def.filter.clone(),
+            units: UnitContext::new(headers),
+        }
+    }
+
+    fn matches(&self, headers: &Headers) -> bool {
+        let def = &headers.main_frame_def;
+
+        self.fields
+            .iter()
+            .copied()
+            .eq(def.fields.iter().map(MainFieldDef::field_unit))
+            && self.filter == def.filter
+            && self.units == UnitContext::new(headers)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct RawMainFrame {
+    pub(crate) intra: bool,
+    pub(crate) iteration: u32,
+    pub(crate) time: u64,
+    pub(crate) values: Vec<u32>,
+}
+
+impl RawMainFrame {
+    fn get(
+        &self,
+        index: usize,
+        filter: &AppliedFilter,
+        field: impl Fn(usize) -> FieldUnit,
+        units: &UnitContext,
+    ) -> Option<MainValue> {
         let value = match index {
-            0 => MainValue::Unsigned(self.raw.iteration),
-            1 => MainValue::FrameTime(Time::from_raw(self.raw.time, self.headers)),
+            0 => MainValue::Unsigned(self.iteration),
+            1 => MainValue::FrameTime(Time::from_raw(self.time, units)),
             _ => {
-                let index = self.headers.main_frame_def.filter.get(index - 2)?;
-                let def = &self.headers.main_frame_def.fields[index];
-                let raw = self.raw.values[index];
-                match def.unit {
+                let index = filter.get(index - 2)?;
+                let FieldUnit {
+                    unit,
+                    signed,
+                    index: name_index,
+                } = field(index);
+                let raw = self.values[index];
+                match unit {
                     MainUnit::Amperage => {
-                        debug_assert!(def.signed);
+                        debug_assert!(signed);
                         let raw = as_i32(raw);
-                        MainValue::Amperage(ElectricCurrent::from_raw(raw, self.headers))
+                        MainValue::Amperage(ElectricCurrent::from_raw(raw, units))
                     }
                     MainUnit::Voltage => {
-                        debug_assert!(!def.signed);
-                        MainValue::Voltage(ElectricPotential::from_raw(raw, self.headers))
+                        debug_assert!(!signed);
+                        MainValue::Voltage(ElectricPotential::from_raw(raw, units))
                     }
                     MainUnit::Acceleration => {
-                        debug_assert!(def.signed);
+                        debug_assert!(signed);
                         let raw = as_i32(raw);
-                        MainValue::Acceleration(Acceleration::from_raw(raw, self.headers))
+                        MainValue::Acceleration(Acceleration::from_raw(raw, units))
                     }
                     MainUnit::Rotation => {
-                        debug_assert!(def.signed);
+                        debug_assert!(signed);
+                        let raw = as_i32(raw);
+                        MainValue::Rotation(AngularVelocity::from_raw(raw, units))
+                    }
+                    MainUnit::Frequency => {
                         let raw = as_i32(raw);
-                        MainValue::Rotation(AngularVelocity::from_raw(raw, self.headers))
+                        MainValue::Frequency(Frequency::from_raw(raw, units))
                     }
-                    MainUnit::Unitless => MainValue::new_unitless(raw, def.signed),
+                    MainUnit::MotorOutput => MainValue::Percent(new_motor_output(raw, units)),
+                    MainUnit::RcCommand => {
+                        let raw = as_i32(raw);
+                        MainValue::Rotation(new_rc_command(raw, name_index, units))
+                    }
+                    MainUnit::Setpoint => MainValue::Rotation(new_setpoint(as_i32(raw))),
+                    MainUnit::Erpm => MainValue::Frequency(new_erpm(raw, units)),
+                    MainUnit::Altitude => {
+                        let raw = as_i32(raw);
+                        MainValue::Altitude(Length::from_raw(raw, units))
+                    }
+                    MainUnit::Rssi => MainValue::Percent(new_rssi(raw)),
+                    MainUnit::Unitless => MainValue::new_unitless(raw, signed),
                     MainUnit::FrameTime => unreachable!(),
                 }
             }
@@ -62,23 +184,16 @@ fn get(&self, index: usize) -> Option<MainValue> {
 
         Some(value)
     }
-}
 
-impl<'data, 'headers, 'parser> MainFrame<'data, 'headers, 'parser> {
-    pub(crate) fn new(headers: &'headers Headers<'data>, raw: &'parser RawMainFrame) -> Self {
-        Self { headers, raw }
+    pub(crate) const fn new(intra: bool, iteration: u32, time: u64, values: Vec<u32>) -> Self {
+        Self {
+            intra,
+            iteration,
+            time,
+            values,
+        }
     }
-}
 
-#[derive(Debug, Clone)]
-pub(crate) struct RawMainFrame {
-    intra: bool,
-    pub(crate) iteration: u32,
-    pub(crate) time: u64,
-    pub(crate) values: Vec<u32>,
-}
-
-impl RawMainFrame {
     pub(crate) fn parse(
         data: &mut Reader,
         headers: &Headers,
@@ -89,13 +204,62 @@ pub(crate) fn parse(
         let def = &headers.main_frame_def;
 
         if kind == FrameKind::Data(DataFrameKind::Intra) {
-            def.parse_intra(data, headers, last)
+            def.parse_intra(data, headers, last, history.last_time())
         } else {
-            let skipped = 0; // FIXME
-
+            let skipped = skipped_frames(headers, last);
             def.parse_inter(data, headers, last, history.last_last(), skipped)
         }
     }
+
+    pub(crate) fn encode(
+        &self,
+        out: &mut Vec<u8>,
+        headers: &Headers,
+        history: &MainFrameHistory,
+    ) -> WriteResult<()> {
+        let last = history.last();
+        let def = &headers.main_frame_def;
+
+        if self.intra {
+            def.encode_intra(out, headers, last, history.last_time(), self)
+        } else {
+            let skipped = skipped_frames(headers, last);
+            def.encode_inter(out, headers, last, history.last_last(), skipped, self)
+        }
+    }
+}
+
+fn skipped_frames(headers: &Headers, last: Option<&RawMainFrame>) -> u32 {
+    match (last, headers.inter_interval) {
+        (Some(last), Some(inter)) => {
+            let intra = headers.intra_interval.unwrap_or(DEFAULT_INTRA_INTERVAL);
+            count_skipped_frames(last.iteration, intra, inter)
+        }
+        _ => 0,
+    }
+}
+
+/// Default for a missing `I interval` header, matching `blackbox_decode`.
+const DEFAULT_INTRA_INTERVAL: u16 = 32;
+
+/// Counts the loop iterations after `last_iteration` that were not logged
+/// because of the configured logging rate.
+fn count_skipped_frames(last_iteration: u32, intra: u16, inter: InterInterval) -> u32 {
+    let intra = u32::from(intra.max(1));
+    let num = u32::from(inter.num.max(1));
+    let denom = u32::from(inter.denom.max(1));
+
+    let should_have_frame = |iteration: u32| (iteration % intra + num - 1) % denom < num;
+
+    let mut skipped = 0;
+    let mut iteration = last_iteration.wrapping_add(1);
+    // The pattern repeats every intra interval, so this must terminate by then
+    while !should_have_frame(iteration) && skipped < intra {
+        skipped += 1;
+        iteration = iteration.wrapping_add(1);
+    }
+
+    skipped
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -105,6 +269,9 @@ pub enum MainValue {
     Voltage(ElectricPotential),
     Acceleration(Acceleration),
     Rotation(AngularVelocity),
+    Frequency(Frequency),
+    Percent(Ratio),
+    Altitude(Length),
     Unsigned(u32),
     Signed(i32),
 }
@@ -126,6 +293,17 @@ pub enum MainUnit {
     Voltage,
     Acceleration,
     Rotation,
+    Frequency,
+    /// `motor[n]` as a fraction of the `motorOutput` range
+    MotorOutput,
+    /// `rcCommand[n]` for roll, pitch & yaw, converted to a rotation rate using
+    /// the configured rates
+    RcCommand,
+    /// `setpoint[n]` for roll, pitch & yaw
+    Setpoint,
+    Erpm,
+    Altitude,
+    Rssi,
     Unitless,
 }
 
@@ -159,7 +337,7 @@ fn get<'a>(&'a self, index: usize) -> Option<(&'data str, MainUnit)>
             _ => self.fields.get(self.filter.get(index - 2)?),
         };
 
-        field.map(|f| (f.name, f.unit))
+        field.map(|f| (f.display_name, f.unit))
     }
 
     fn clear_filter(&mut self) {
@@ -170,6 +348,8 @@ fn clear_filter(&mut self) {
     /// previous filter.
     ///
     /// **Note:** `loopIteration` and `time` fields will always be included.
+    /// Fields are matched by the names in the log headers, so `debug` selects
+    /// all `debug[n]` fields regardless of the [`DebugMode`].
     fn apply_filter(&mut self, filter: &FieldFilter) {
         self.filter = filter.apply(self.fields.iter().map(|f| f.name));
     }
@@ -181,14 +361,14 @@ pub fn iter(&self) -> impl Iterator<Item = (&str, MainUnit)> + '_ {
         iter::once(&self.iteration)
             .chain(iter::once(&self.time))
             .chain(self.filter.iter().map(|i| &self.fields[i]))
-            .map(|field| (field.name, field.unit))
+            .map(|field| (field.display_name, field.unit))
     }
 
     /// Iterates over the names of each field.
     pub fn iter_names(&self) -> impl Iterator<Item = &str> {
-        iter::once(self.iteration.name)
-            .chain(iter::once(self.time.name))
-            .chain(self.filter.iter().map(|i| self.fields[i].name))
+        iter::once(self.iteration.display_name)
+            .chain(iter::once(self.time.display_name))
+            .chain(self.filter.iter().map(|i| self.fields[i].display_name))
     }
 
     pub(crate) fn builder() -> MainFrameDefBuilder<'data> {
@@ -235,23 +415,18 @@ pub(crate) fn parse_intra(
         data: &mut Reader,
         headers: &Headers,
         last: Option<&RawMainFrame>,
+        last_time: Option<u64>,
     ) -> InternalResult<RawMainFrame> {
-        fn get_update_ctx(
-            last: Option<&'_ RawMainFrame>,
-        ) -> impl Fn(&mut PredictorContext, usize) + '_ {
-            move |ctx, i| ctx.set_last(last.map(|l| l.values[i]))
-        }
-
         let iteration = decode::variable(data)?;
         tracing::trace!(iteration);
-        let time = decode::variable(data)?.into();
+        let time = expand_time(decode::variable(data)?, last_time);
         tracing::trace!(time);
 
         let values = super::parse_impl(
             PredictorContext::new(headers),
-            &read_field_values(data, &self.fields, |f| f.encoding_intra)?,
+            &read_field_values(data, headers.version, &self.fields, |f| f.encoding_intra)?,
             self.fields.iter().map(IntraFieldDef),
-            get_update_ctx(last),
+            intra_update_ctx(last),
         );
 
         Ok(RawMainFrame {
@@ -271,25 +446,11 @@ pub(crate) fn parse_inter(
         last_last: Option<&RawMainFrame>,
         skipped_frames: u32,
     ) -> InternalResult<RawMainFrame> {
-        fn get_update_ctx<'a>(
-            last: Option<&'a RawMainFrame>,
-            last_last: Option<&'a RawMainFrame>,
-        ) -> impl Fn(&mut PredictorContext<'_, '_>, usize) + 'a {
-            move |ctx, i| {
-                ctx.set_last_2(last.map(|l| l.values[i]), last_last.map(|l| l.values[i]));
-            }
-        }
-
         let iteration = 1 + last.map_or(0, |f| f.iteration) + skipped_frames;
         tracing::trace!(iteration);
 
         let time = {
-            // Get the time from last_last if last was an interframe
-            let last_last = last
-                .filter(|f| !f.intra)
-                .and_then(|_| last_last.map(|f| f.time));
-
-            let time = predictor::straight_line(last.map(|f| f.time), last_last);
+            let time = predict_inter_time(last, last_last);
             let offset = decode::variable_signed(data)?;
             let time = time.saturating_add_signed(offset.into());
 
@@ -299,9 +460,9 @@ fn get_update_ctx<'a>(
 
         let values = super::parse_impl(
             PredictorContext::with_skipped(headers, skipped_frames),
-            &read_field_values(data, &self.fields, |f| f.encoding_inter)?,
+            &read_field_values(data, headers.version, &self.fields, |f| f.encoding_inter)?,
             self.fields.iter().map(InterFieldDef),
-            get_update_ctx(last, last_last),
+            inter_update_ctx(last, last_last),
         );
 
         Ok(RawMainFrame {
@@ -311,17 +472,139 @@ fn get_update_ctx<'a>(
             values,
         })
     }
+
+    pub(crate) fn encode_intra(
+        &self,
+        out: &mut Vec<u8>,
+        headers: &Headers,
+        last: Option<&RawMainFrame>,
+        last_time: Option<u64>,
+        frame: &RawMainFrame,
+    ) -> WriteResult<()> {
+        let kind = DataFrameKind::Intra;
+        super::check_field_count(kind, self.fields.len(), frame.values.len())?;
+
+        let time = truncate_time(frame.time);
+        if expand_time(time, last_time) != frame.time {
+            return Err(super::unencodable_error(kind, self.time.name));
+        }
+
+        encode::variable(out, frame.iteration);
+        encode::variable(out, time);
+
+        let raw = super::encode_impl(
+            PredictorContext::new(headers),
+            &frame.values,
+            self.fields.iter().map(IntraFieldDef),
+            intra_update_ctx(last),
+        );
+
+        super::write_field_values(
+            out,
+            headers.version,
+            &self.fields,
+            |f| f.encoding_intra,
+            &raw,
+        )
+        .map_err(|i| super::unencodable_error(kind, self.fields[i].name))
+    }
+
+    pub(crate) fn encode_inter(
+        &self,
+        out: &mut Vec<u8>,
+        headers: &Headers,
+        last: Option<&RawMainFrame>,
+        last_last: Option<&RawMainFrame>,
+        skipped_frames: u32,
+        frame: &RawMainFrame,
+    ) -> WriteResult<()> {
+        let kind = DataFrameKind::Inter;
+        super::check_field_count(kind, self.fields.len(), frame.values.len())?;
+
+        let iteration = 1 + last.map_or(0, |f| f.iteration) + skipped_frames;
+        if frame.iteration != iteration {
+            return Err(super::unencodable_error(kind, self.iteration.name));
+        }
+
+        let offset = {
+            let time = predict_inter_time(last, last_last);
+            let offset = i128::from(frame.time) - i128::from(time);
+            i32::try_from(offset).map_err(|_| super::unencodable_error(kind, self.time.name))?
+        };
+        encode::variable_signed(out, offset);
+
+        let raw = super::encode_impl(
+            PredictorContext::with_skipped(headers, skipped_frames),
+            &frame.values,
+            self.fields.iter().map(InterFieldDef),
+            inter_update_ctx(last, last_last),
+        );
+
+        super::write_field_values(
+            out,
+            headers.version,
+            &self.fields,
+            |f| f.encoding_inter,
+            &raw,
+        )
+        .map_err(|i| super::unencodable_error(kind, self.fields[i].name))
+    }
+}
+
+fn intra_update_ctx(last: Option<&'_ RawMainFrame>) -> impl Fn(&mut PredictorContext, usize) + '_ {
+    move |ctx, i| ctx.set_last(last.map(|l| l.values[i]))
+}
+
+fn inter_update_ctx<'a>(
+    last: Option<&'a RawMainFrame>,
+    last_last: Option<&'a RawMainFrame>,
+) -> impl Fn(&mut PredictorContext<'_, '_>, usize) + 'a {
+    move |ctx, i| {
+        ctx.set_last_2(last.map(|l| l.values[i]), last_last.map(|l| l.values[i]));
+    }
+}
+
+fn predict_inter_time(last: Option<&RawMainFrame>, last_last: Option<&RawMainFrame>) -> u64 {
+    // Get the time from last_last if last was an interframe
+    let last_last = last
+        .filter(|f| !f.intra)
+        .and_then(|_| last_last.map(|f| f.time));
+
+    predictor::straight_line(last.map(|f| f.time), last_last)
 }
 
 #[derive(Debug, Clone)]
 pub(crate) struct MainFieldDef<'data> {
     pub(crate) name: &'data str,
+    /// The name exposed to users, which differs from `name` for `debug[n]`
+    /// fields with a known meaning.
+    pub(crate) display_name: &'data str,
     predictor_intra: Predictor,
     predictor_inter: Predictor,
     encoding_intra: Encoding,
     encoding_inter: Encoding,
     pub(crate) signed: bool,
     pub(crate) unit: MainUnit,
+    /// The index from the name, eg `1` for `rcCommand[1]`.
+    index: usize,
+}
+
+impl MainFieldDef<'_> {
+    fn field_unit(&self) -> FieldUnit {
+        FieldUnit {
+            unit: self.unit,
+            signed: self.signed,
+            index: self.index,
+        }
+    }
+}
+
+/// What is needed from a [`MainFieldDef`] to convert its raw values.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct FieldUnit {
+    unit: MainUnit,
+    signed: bool,
+    index: usize,
 }
 
 #[derive(Debug)]
@@ -398,7 +681,7 @@ pub(crate) fn update(
         }
     }
 
-    pub(crate) fn parse(self) -> HeadersParseResult<MainFrameDef<'data>> {
+    pub(crate) fn parse(self, units: &UnitHeaders) -> HeadersParseResult<MainFrameDef<'data>> {
         let kind_intra = DataFrameKind::Intra;
         let kind_inter = DataFrameKind::Inter;
 
@@ -417,14 +700,21 @@ pub(crate) fn parse(self) -> HeadersParseResult<MainFrameDef<'data>> {
                     ((name, signed), (predictor_intra, predictor_inter)),
                     (encoding_intra, encoding_inter),
                 )| {
+                    let (display_name, unit) = units
+                        .debug_mode
+                        .and_then(|mode| debug_field(mode, name))
+                        .unwrap_or_else(|| (name, unit_from_name(name, units)));
+
                     Ok(MainFieldDef {
                         name,
+                        display_name,
                         predictor_intra: predictor_intra?,
                         predictor_inter: predictor_inter?,
                         encoding_intra: encoding_intra?,
                         encoding_inter: encoding_inter?,
                         signed,
-                        unit: unit_from_name(name),
+                        unit,
+                        index: name_index(name).unwrap_or(0),
                     })
                 },
             );
@@ -485,13 +775,146 @@ pub(crate) fn parse(self) -> HeadersParseResult<MainFrameDef<'data>> {
     }
 }
 
-fn unit_from_name(name: &str) -> MainUnit {
+/// The headers that decide the units of main frame fields.
+#[derive(Debug, Default)]
+pub(crate) struct UnitHeaders {
+    pub(crate) debug_mode: Option<DebugMode>,
+    /// Whether `motorOutput` is known.
+    pub(crate) has_motor_output: bool,
+    /// Whether all rates needed to convert `rcCommand` are known.
+    pub(crate) has_rates: bool,
+}
+
+fn unit_from_name(name: &str, units: &UnitHeaders) -> MainUnit {
+    // Only roll, pitch & yaw are rotation rates, not throttle
+    let is_axis = matches!(name_index(name), Some(0..=2));
+
     match to_base_field(name) {
         "time" => MainUnit::FrameTime,
         "vbat" | "vbatLatest" => MainUnit::Voltage,
         "amperageLatest" => MainUnit::Amperage,
         "accSmooth" => MainUnit::Acceleration,
         "gyroADC" => MainUnit::Rotation,
+        "motor" if units.has_motor_output => MainUnit::MotorOutput,
+        "rcCommand" if is_axis && units.has_rates => MainUnit::RcCommand,
+        "setpoint" if is_axis => MainUnit::Setpoint,
+        "eRPM" => MainUnit::Erpm,
+        "BaroAlt" => MainUnit::Altitude,
+        "rssi" => MainUnit::Rssi,
+        // The PID terms are in the firmware's internal mixer scale, and
+        // converting raw magnetometer readings needs the sensor's gain, which
+        // is not logged
+        "axisP" | "axisI" | "axisD" | "axisF" | "magADC" => MainUnit::Unitless,
         _ => MainUnit::Unitless,
     }
 }
+
+/// Returns the index from a field name, eg `1` for `rcCommand[1]`.
+fn name_index(name: &str) -> Option<usize> {
+    let (_, index) = name.strip_suffix(']')?.split_once('[')?;
+    index.parse().ok()
+}
+
+/// Returns the meaning of a `debug[n]` field in the given debug mode, if it is
+/// known.
+fn debug_field(mode: DebugMode, name: &str) -> Option<(&'static str, MainUnit)> {
+    if to_base_field(name) != "debug" {
+        return None;
+    }
+    let index = name_index(name)?;
+
+    let field = match (mode, index) {
+        (DebugMode::GyroFiltered, 0) => ("gyroFiltered[0]", MainUnit::Rotation),
+        (DebugMode::GyroFiltered, 1) => ("gyroFiltered[1]", MainUnit::Rotation),
+        (DebugMode::GyroFiltered, 2) => ("gyroFiltered[2]", MainUnit::Rotation),
+
+        (DebugMode::GyroScaled, 0) => ("gyroScaled[0]", MainUnit::Rotation),
+        (DebugMode::GyroScaled, 1) => ("gyroScaled[1]", MainUnit::Rotation),
+        (DebugMode::GyroScaled, 2) => ("gyroScaled[2]", MainUnit::Rotation),
+
+        (DebugMode::GyroRaw, 0) => ("gyroRaw[0]", MainUnit::Unitless),
+        (DebugMode::GyroRaw, 1) => ("gyroRaw[1]", MainUnit::Unitless),
+        (DebugMode::GyroRaw, 2) => ("gyroRaw[2]", MainUnit::Unitless),
+
+        (DebugMode::FftFreq, 0) => ("dynNotchCenter[0]", MainUnit::Frequency),
+        (DebugMode::FftFreq, 1) => ("dynNotchCenter[1]", MainUnit::Frequency),
+        (DebugMode::FftFreq, 3) => ("gyroPreDynNotch", MainUnit::Rotation),
+
+        (DebugMode::DynLpf, 1) => ("gyroLowpassCutoff", MainUnit::Frequency),
+        (DebugMode::DynLpf, 2) => ("dtermLowpassCutoff", MainUnit::Frequency),
+
+        (DebugMode::RpmFilter, 0) => ("motorFrequency[0]", MainUnit::Frequency),
+        (DebugMode::RpmFilter, 1) => ("motorFrequency[1]", MainUnit::Frequency),
+        (DebugMode::RpmFilter, 2) => ("motorFrequency[2]", MainUnit::Frequency),
+        (DebugMode::RpmFilter, 3) => ("motorFrequency[3]", MainUnit::Frequency),
+
+        (DebugMode::DshotRpmTelemetry, 0) => ("dshotErpm[0]", MainUnit::Erpm),
+        (DebugMode::DshotRpmTelemetry, 1) => ("dshotErpm[1]", MainUnit::Erpm),
+        (DebugMode::DshotRpmTelemetry, 2) => ("dshotErpm[2]", MainUnit::Erpm),
+        (DebugMode::DshotRpmTelemetry, 3) => ("dshotErpm[3]", MainUnit::Erpm),
+
+        (DebugMode::DLpf, 0) => ("dtermGyroRaw[0]", MainUnit::Rotation),
+        (DebugMode::DLpf, 1) => ("dtermGyroRaw[1]", MainUnit::Rotation),
+        (DebugMode::DLpf, 2) => ("dtermGyroFiltered[0]", MainUnit::Rotation),
+        (DebugMode::DLpf, 3) => ("dtermGyroFiltered[1]", MainUnit::Rotation),
+
+        _ => return None,
+    };
+
+    Some(field)
+}
+
+#[cfg(test)]
+mod tests {
+    use test_case::case;
+
+    use super::*;
+
+    #[case(0, 32, 1, 1 => 0 ; "every frame")]
+    #[case(0, 32, 1, 2 => 1 ; "half")]
+    #[case(1, 32, 1, 2 => 0 ; "half after skip")]
+    #[case(0, 256, 1, 16 => 15 ; "sixteenth")]
+    #[case(240, 256, 1, 16 => 15 ; "sixteenth before intra")]
+    #[case(2, 32, 2, 3 => 0 ; "two thirds")]
+    #[case(0, 32, 2, 3 => 1 ; "two thirds skip")]
+    #[case(u32::MAX, 32, 1, 2 => 0 ; "iteration overflow")]
+    fn skipped_frames(last: u32, intra: u16, num: u16, denom: u16) -> u32 {
+        count_skipped_frames(last, intra, InterInterval { num, denom })
+    }
+
+    #[case(DebugMode::GyroScaled, "debug[1]" => Some(("gyroScaled[1]", MainUnit::Rotation)); "gyro scaled")]
+    #[case(DebugMode::RpmFilter, "debug[3]" => Some(("motorFrequency[3]", MainUnit::Frequency)); "rpm filter")]
+    #[case(DebugMode::FftFreq, "debug[2]" => None; "unknown index")]
+    #[case(DebugMode::Disabled, "debug[0]" => None; "no debug mode")]
+    #[case(DebugMode::GyroScaled, "gyroADC[0]" => None; "not debug")]
+    fn debug_fields(mode: DebugMode, name: &str) -> Option<(&'static str, MainUnit)> {
+        debug_field(mode, name)
+    }
+
+    #[case("motor[0]" => MainUnit::MotorOutput; "motor")]
+    #[case("rcCommand[2]" => MainUnit::RcCommand; "rc command")]
+    #[case("rcCommand[3]" => MainUnit::Unitless; "rc command throttle")]
+    #[case("setpoint[1]" => MainUnit::Setpoint; "setpoint")]
+    #[case("eRPM[3]" => MainUnit::Erpm; "erpm")]
+    #[case("BaroAlt" => MainUnit::Altitude; "baro altitude")]
+    #[case("rssi" => MainUnit::Rssi; "rssi")]
+    #[case("axisP[0]" => MainUnit::Unitless; "pid term")]
+    #[case("magADC[1]" => MainUnit::Unitless; "magnetometer")]
+    #[case("flightModeFlags" => MainUnit::Unitless; "unitless")]
+    fn units(name: &str) -> MainUnit {
+        let units = UnitHeaders {
+            debug_mode: None,
+            has_motor_output: true,
+            has_rates: true,
+        };
+
+        unit_from_name(name, &units)
+    }
+
+    #[test]
+    fn units_missing_headers() {
+        let units = UnitHeaders::default();
+        assert_eq!(MainUnit::Unitless, unit_from_name("motor[0]", &units));
+        assert_eq!(MainUnit::Unitless, unit_from_name("rcCommand[0]", &units));
+    }
+}
@@ -14,13 +14,22 @@
 use core::fmt;
 use core::iter::Peekable;
 
-pub use self::gps::{GpsFrame, GpsFrameDef, GpsUnit, GpsValue};
-pub(crate) use self::gps_home::{GpsHomeFrame, GpsPosition};
-pub use self::main::{MainFrame, MainFrameDef, MainUnit, MainValue};
-pub use self::slow::{SlowFrame, SlowFrameDef, SlowUnit, SlowValue};
+pub use self::gps::{GpsFrame, GpsFrameDef, GpsUnit, GpsValue, OwnedGpsFrame, OwnedGpsFrameDef};
+pub(crate) use self::gps_home::GpsHomeFrame;
+pub use self::gps_home::GpsPosition;
+pub use self::main::{
+    MainFrame, MainFrameDef, MainUnit, MainValue, OwnedMainFrame, OwnedMainFrameDef,
+};
+pub use self::slow::{
+    OwnedSlowFrame, OwnedSlowFrameDef, SlowFrame, SlowFrameDef, SlowUnit, SlowValue,
+};
+use crate::headers::LogVersion;
+use crate::parser::decode::BitReader;
+use crate::parser::encode::BitWriter;
 use crate::parser::{Encoding, InternalResult};
 use crate::predictor::{Predictor, PredictorContext};
 use crate::units::prelude::*;
+use crate::writer::{WriteError, WriteResult};
 use crate::{units, FieldFilter, HeadersParseError, HeadersParseResult, Reader};
 
 mod seal {
@@ -201,6 +210,8 @@ pub enum Unit {
     Voltage,
     Acceleration,
     Rotation,
+    Frequency,
+    Percent,
     FlightMode,
     State,
     FailsafePhase,
@@ -220,6 +231,10 @@ fn from(unit: MainUnit) -> Self {
             MainUnit::Voltage => Self::Voltage,
             MainUnit::Acceleration => Self::Acceleration,
             MainUnit::Rotation => Self::Rotation,
+            MainUnit::Frequency | MainUnit::Erpm => Self::Frequency,
+            MainUnit::MotorOutput | MainUnit::Rssi => Self::Percent,
+            MainUnit::RcCommand | MainUnit::Setpoint => Self::Rotation,
+            MainUnit::Altitude => Self::Altitude,
             MainUnit::Unitless => Self::Unitless,
         }
     }
@@ -257,6 +272,8 @@ pub enum Value {
     Voltage(ElectricPotential),
     Acceleration(Acceleration),
     Rotation(AngularVelocity),
+    Frequency(Frequency),
+    Percent(Ratio),
     FlightMode(units::FlightModeSet),
     State(units::StateSet),
     FailsafePhase(units::FailsafePhase),
@@ -277,6 +294,9 @@ fn from(value: MainValue) -> Self {
             MainValue::Voltage(v) => Self::Voltage(v),
             MainValue::Acceleration(a) => Self::Acceleration(a),
             MainValue::Rotation(r) => Self::Rotation(r),
+            MainValue::Frequency(f) => Self::Frequency(f),
+            MainValue::Percent(p) => Self::Percent(p),
+            MainValue::Altitude(a) => Self::Altitude(a),
             MainValue::Unsigned(x) => Self::Unsigned(x),
             MainValue::Signed(x) => Self::Signed(x),
         }
@@ -411,17 +431,21 @@ fn count_fields_with_same_encoding(
 
 fn read_field_values<T>(
     data: &mut Reader,
+    version: LogVersion,
     fields: &[T],
     get_encoding: impl Fn(&T) -> Encoding,
 ) -> InternalResult<Vec<u32>> {
     let mut encodings = fields.iter().map(get_encoding).peekable();
     let mut values = Vec::with_capacity(encodings.len());
+    // Shared by all Elias delta fields and dropped at the end of the frame to
+    // discard any padding bits
+    let mut bits = BitReader::new();
 
     while let Some(encoding) = encodings.next() {
         let extra = encoding.max_chunk_size() - 1;
         let extra = count_fields_with_same_encoding(&mut encodings, extra, encoding);
 
-        encoding.decode_into(data, extra, &mut values)?;
+        encoding.decode_into(data, &mut bits, version, extra, &mut values)?;
     }
 
     debug_assert_eq!(values.len(), fields.len());
@@ -429,6 +453,75 @@ fn read_field_values<T>(
     Ok(values)
 }
 
+/// Inverse of [`read_field_values`]. On failure, returns the index of the first
+/// field that could not be encoded.
+fn write_field_values<T>(
+    out: &mut Vec<u8>,
+    version: LogVersion,
+    fields: &[T],
+    get_encoding: impl Fn(&T) -> Encoding,
+    values: &[u32],
+) -> Result<(), usize> {
+    debug_assert_eq!(values.len(), fields.len());
+
+    let mut encodings = fields.iter().map(get_encoding).peekable();
+    let mut bits = BitWriter::new();
+    let mut start = 0;
+
+    while let Some(encoding) = encodings.next() {
+        let extra = encoding.max_chunk_size() - 1;
+        let extra = count_fields_with_same_encoding(&mut encodings, extra, encoding);
+
+        let end = start + extra + 1;
+        encoding
+            .encode_into(out, &mut bits, version, &values[start..end])
+            .map_err(|i| start + i)?;
+        start = end;
+    }
+
+    Ok(())
+}
+
+fn check_field_count(kind: DataFrameKind, expected: usize, found: usize) -> WriteResult<()> {
+    if expected == found {
+        Ok(())
+    } else {
+        Err(WriteError::FieldCount {
+            frame: FrameKind::Data(kind),
+            expected,
+            found,
+        })
+    }
+}
+
+fn unencodable_error(kind: DataFrameKind, field: &str) -> WriteError {
+    WriteError::Unencodable {
+        frame: FrameKind::Data(kind),
+        field: field.to_owned(),
+    }
+}
+
+/// Inverse of [`parse_impl`], returning the values to be encoded.
+fn encode_impl<'data, F: FieldDef<'data>>(
+    mut ctx: PredictorContext<'_, 'data>,
+    values: &[u32],
+    fields: impl IntoIterator<Item = F>,
+    update_ctx: impl Fn(&mut PredictorContext<'_, 'data>, usize),
+) -> Vec<u32> {
+    fields
+        .into_iter()
+        .enumerate()
+        .map(|(i, field)| {
+            update_ctx(&mut ctx, i);
+
+            let signed = field.encoding().is_signed();
+            field
+                .predictor()
+                .unapply(values[i], signed, Some(&values[..i]), &ctx)
+        })
+        .collect()
+}
+
 fn parse_impl<'data, F: FieldDef<'data>>(
     mut ctx: PredictorContext<'_, 'data>,
     raw: &[u32],
@@ -461,3 +554,163 @@ fn parse_impl<'data, F: FieldDef<'data>>(
 
     values
 }
+
+#[cfg(test)]
+mod tests {
+    use alloc::sync::Arc;
+    use alloc::vec::Vec;
+
+    use super::*;
+    use crate::data::ParseEvent;
+    use crate::{DataParser, File, Headers};
+
+    fn assert_owned<T: Frame + Clone + Send + Sync + 'static>() {}
+
+    fn read(bytes: &[u8], encodings: &[Encoding]) -> (Vec<u32>, usize) {
+        let mut data = Reader::new(bytes);
+        let values = read_field_values(&mut data, LogVersion::V2, encodings, |&e| e).unwrap();
+        (values, data.position())
+    }
+
+    #[test]
+    fn elias_delta_fields_share_bits() {
+        // 1, -1 (zig-zag encoded as 1) & 0 are 0100, 0100 & 1, followed by 7 padding
+        // bits
+        let encodings = [
+            Encoding::EliasDelta,
+            Encoding::EliasDeltaSigned,
+            Encoding::EliasDelta,
+        ];
+
+        let (values, consumed) = read(&[0b0100_0100, 0b1000_0000], &encodings);
+        assert_eq!(alloc::vec![1, u32::MAX, 0], values);
+        assert_eq!(2, consumed);
+    }
+
+    #[test]
+    fn elias_delta_split_by_other_encoding() {
+        let encodings = [
+            Encoding::EliasDelta,
+            Encoding::Variable,
+            Encoding::EliasDeltaSigned,
+        ];
+
+        // The second Elias delta field uses the remaining bits of the first byte
+        let (values, consumed) = read(&[0b0100_0100, 5], &encodings);
+        assert_eq!(alloc::vec![1, 5, u32::MAX], values);
+        assert_eq!(2, consumed);
+    }
+
+    #[test]
+    fn elias_delta_round_trip() {
+        let encodings = [
+            Encoding::EliasDelta,
+            Encoding::EliasDeltaSigned,
+            Encoding::Variable,
+            Encoding::EliasDelta,
+            Encoding::EliasDeltaSigned,
+        ];
+        let values = [100, crate::utils::as_u32(-3), 300, 0, 7];
+
+        let mut out = Vec::new();
+        write_field_values(&mut out, LogVersion::V2, &encodings, |&e| e, &values).unwrap();
+
+        let (decoded, consumed) = read(&out, &encodings);
+        assert_eq!(&values[..], &decoded[..]);
+        assert_eq!(out.len(), consumed);
+    }
+
+    #[test]
+    fn owned_frames_are_send() {
+        assert_owned::<OwnedMainFrame>();
+        assert_owned::<OwnedSlowFrame>();
+        assert_owned::<OwnedGpsFrame>();
+    }
+
+    #[test]
+    fn owned_frames_match() {
+        let file = File::new(include_bytes!("../../tests/logs/gps.bbl"));
+        let mut reader = file.get_reader(0);
+        let mut headers = Headers::parse(&mut reader).unwrap();
+        headers.main_frame_def.apply_filter(&["axisP"].into());
+        headers.slow_frame_def.apply_filter(&["stateFlags"].into());
+
+        let main_def = Arc::new(OwnedMainFrameDef::new(&headers));
+        let slow_def = Arc::new(OwnedSlowFrameDef::new(&headers));
+        let gps_def = Arc::new(OwnedGpsFrameDef::new(&headers).unwrap());
+
+        let mut main = Vec::new();
+        let mut slow = Vec::new();
+        let mut gps = Vec::new();
+
+        let mut parser = DataParser::new(reader, &headers);
+        while let Some(event) = parser.next() {
+            match event {
+                ParseEvent::Main(frame) => {
+                    main.push((
+                        frame.iter().collect::<Vec<_>>(),
+                        frame.into_owned(&main_def).unwrap(),
+                    ));
+                }
+                ParseEvent::Slow(frame) => {
+                    slow.push((
+                        frame.iter().collect::<Vec<_>>(),
+                        frame.into_owned(&slow_def).unwrap(),
+                    ));
+                }
+                ParseEvent::Gps(frame) => {
+                    gps.push((frame.iter().collect::<Vec<_>>(), frame.into_owned(&gps_def).unwrap()));
+                }
+                ParseEvent::Event(_) => {}
+            }
+        }
+        drop(headers);
+
+        assert!(!main.is_empty() && !slow.is_empty() && !gps.is_empty());
+        for (expected, frame) in main {
+            assert_eq!(3, expected.len());
+            assert_eq!(expected, frame.iter().collect::<Vec<_>>());
+        }
+        for (expected, frame) in slow {
+            assert_eq!(1, expected.len());
+            assert_eq!(expected, frame.iter().collect::<Vec<_>>());
+        }
+        for (expected, frame) in gps {
+            assert_eq!(expected, frame.iter().collect::<Vec<_>>());
+        }
+    }
+
+    #[test]
+    fn owned_frame_def_mismatch() {
+        let file = File::new(include_bytes!("../../tests/logs/gps.bbl"));
+        let mut reader = file.get_reader(0);
+        let mut headers = Headers::parse(&mut reader).unwrap();
+
+        let unfiltered = Arc::new(OwnedMainFrameDef::new(&headers));
+        headers.main_frame_def.apply_filter(&["motor"].into());
+        let filtered = Arc::new(OwnedMainFrameDef::new(&headers));
+
+        let other = File::new(include_bytes!("../../tests/logs/error-recovery.bbl"));
+        let other = Headers::parse(&mut other.get_reader(0)).unwrap();
+        let other_slow = Arc::new(OwnedSlowFrameDef::new(&other));
+
+        let mut parser = DataParser::new(reader, &headers);
+        let (mut main, mut slow) = (0, 0);
+        while let Some(event) = parser.next() {
+            match event {
+                ParseEvent::Main(frame) => {
+                    let def = if main % 2 == 0 { &unfiltered } else { &filtered };
+                    assert_eq!(main % 2 == 1, frame.into_owned(def).is_some());
+                    main += 1;
+                }
+                ParseEvent::Slow(frame) => {
+                    assert!(frame.into_owned(&other_slow).is_none());
+                    slow += 1;
+                }
+                _ => {}
+            }
+        }
+
+        assert!(main > 1 && slow > 0);
+    }
+}
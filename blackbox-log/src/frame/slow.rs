@@ -1,3 +1,4 @@
+use alloc::sync::Arc;
 use alloc::vec::Vec;
 
 use tracing::instrument;
@@ -6,14 +7,16 @@
 use crate::filter::{AppliedFilter, FieldFilter};
 use crate::parser::{Encoding, InternalResult};
 use crate::predictor::{Predictor, PredictorContext};
+use crate::units::UnitContext;
 use crate::utils::as_i32;
+use crate::writer::WriteResult;
 use crate::{units, Headers, HeadersParseResult, Reader};
 
 /// Data parsed from a slow frame.
 #[derive(Debug, Clone)]
 pub struct SlowFrame<'data, 'headers> {
     headers: &'headers Headers<'data>,
-    raw: RawSlowFrame,
+    pub(crate) raw: RawSlowFrame,
 }
 
 impl super::seal::Sealed for SlowFrame<'_, '_> {}
@@ -22,14 +25,108 @@ impl super::Frame for SlowFrame<'_, '_> {
     type Value = SlowValue;
 
     fn get(&self, index: usize) -> Option<Self::Value> {
-        let index = self.headers.slow_frame_def.filter.get(index)?;
-        let def = &self.headers.slow_frame_def.fields[index];
-        let raw = self.raw.0[index];
-
-        let firmware = self.headers.firmware_kind;
-        let value = match def.unit {
-            SlowUnit::FlightMode => SlowValue::FlightMode(units::FlightModeSet::new(raw, firmware)),
-            SlowUnit::State => SlowValue::State(units::StateSet::new(raw, firmware)),
+        let def = &self.headers.slow_frame_def;
+        let field = |i: usize| (def.fields[i].unit, def.fields[i].signed);
+
+        self.raw
+            .get(index, &def.filter, field, &UnitContext::new(self.headers))
+    }
+}
+
+impl<'data, 'headers> SlowFrame<'data, 'headers> {
+    pub(crate) fn new(headers: &'headers Headers<'data>, raw: RawSlowFrame) -> Self {
+        Self { headers, raw }
+    }
+
+    /// Converts this frame into an [`OwnedSlowFrame`] that can outlive the
+    /// [`Headers`].
+    ///
+    /// Returns `None` if `def` does not match the [`Headers`] of this frame,
+    /// eg if it was created for another log or before applying a filter.
+    pub fn into_owned(self, def: &Arc<OwnedSlowFrameDef>) -> Option<OwnedSlowFrame> {
+        def.matches(self.headers).then(|| OwnedSlowFrame {
+            def: Arc::clone(def),
+            raw: self.raw,
+        })
+    }
+}
+
+/// An owned version of [`SlowFrame`], created by [`SlowFrame::into_owned`].
+#[derive(Debug, Clone)]
+pub struct OwnedSlowFrame {
+    def: Arc<OwnedSlowFrameDef>,
+    raw: RawSlowFrame,
+}
+
+impl super::seal::Sealed for OwnedSlowFrame {}
+
+impl super::Frame for OwnedSlowFrame {
+    type Value = SlowValue;
+
+    fn get(&self, index: usize) -> Option<Self::Value> {
+        let def = &*self.def;
+        self.raw
+            .get(index, &def.filter, |i| def.fields[i], &def.units)
+    }
+}
+
+/// The parts of a [`SlowFrameDef`] and its [`Headers`] needed by
+/// [`OwnedSlowFrame`]s.
+///
+/// This includes the filter applied at the time it was created.
+#[derive(Debug, Clone)]
+pub struct OwnedSlowFrameDef {
+    /// Unit and signedness of each field
+    fields: Vec<(SlowUnit, bool)>,
+    filter: AppliedFilter,
+    units: UnitContext,
+}
+
+impl OwnedSlowFrameDef {
+    pub fn new(headers: &Headers) -> Self {
+        let def = &headers.slow_frame_def;
+
+        Self {
+            fields: def.fields.iter().map(|f| (f.unit, f.signed)).collect(),
+            filter: def.filter.clone(),
+            units: UnitContext::new(headers),
+        }
+    }
+
+    fn matches(&self, headers: &Headers) -> bool {
+        let def = &headers.slow_frame_def;
+
+        self.fields
+            .iter()
+            .copied()
+            .eq(def.fields.iter().map(|f| (f.unit, f.signed)))
+            && self.filter == def.filter
+            && self.units == UnitContext::new(headers)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct RawSlowFrame(pub(crate) Vec<u32>);
+
+impl RawSlowFrame {
+    fn get(
+        &self,
+        index: usize,
+        filter: &AppliedFilter,
+        field: impl Fn(usize) -> (SlowUnit, bool),
+        units: &UnitContext,
+    ) -> Option<SlowValue> {
+        let index = filter.get(index)?;
+        let (unit, signed) = field(index);
+        let raw = self.0[index];
+
+        let firmware = units.firmware_kind;
+
+        let value = match unit {
+            SlowUnit::FlightMode => {
+                SlowValue::FlightMode(units::FlightModeSet::new(raw, units.flag_layout))
+            }
+            SlowUnit::State => SlowValue::State(units::StateSet::new(raw, units.flag_layout)),
             SlowUnit::FailsafePhase => {
                 SlowValue::FailsafePhase(units::FailsafePhase::new(raw, firmware))
             }
@@ -40,24 +137,13 @@ fn get(&self, index: usize) -> Option<Self::Value> {
 
                 SlowValue::Boolean(raw != 0)
             }
-            SlowUnit::Unitless => SlowValue::new_unitless(raw, def.signed),
+            SlowUnit::Unitless => SlowValue::new_unitless(raw, signed),
         };
 
         Some(value)
     }
 }
 
-impl<'data, 'headers> SlowFrame<'data, 'headers> {
-    pub(crate) fn new(headers: &'headers Headers<'data>, raw: RawSlowFrame) -> Self {
-        Self { headers, raw }
-    }
-}
-
-#[derive(Debug, Clone)]
-pub(crate) struct RawSlowFrame(Vec<u32>);
-
-impl RawSlowFrame {}
-
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum SlowValue {
     FlightMode(units::FlightModeSet),
@@ -166,13 +252,33 @@ pub(crate) fn parse(
     ) -> InternalResult<RawSlowFrame> {
         let values = super::parse_impl(
             PredictorContext::new(headers),
-            &read_field_values(data, &self.fields, |f| f.encoding)?,
+            &read_field_values(data, headers.version, &self.fields, |f| f.encoding)?,
             self.fields.iter(),
             |_, _| {},
         );
 
         Ok(RawSlowFrame(values))
     }
+
+    pub(crate) fn encode(
+        &self,
+        out: &mut Vec<u8>,
+        headers: &Headers,
+        frame: &RawSlowFrame,
+    ) -> WriteResult<()> {
+        let kind = DataFrameKind::Slow;
+        super::check_field_count(kind, self.fields.len(), frame.0.len())?;
+
+        let raw = super::encode_impl(
+            PredictorContext::new(headers),
+            &frame.0,
+            self.fields.iter(),
+            |_, _| {},
+        );
+
+        super::write_field_values(out, headers.version, &self.fields, |f| f.encoding, &raw)
+            .map_err(|i| super::unencodable_error(kind, self.fields[i].name))
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -6,13 +6,15 @@
 
 use hashbrown::HashMap;
 
+use crate::config::{Config, ConfigBuilder};
 use crate::frame::gps::{GpsFrameDef, GpsFrameDefBuilder};
 use crate::frame::gps_home::{GpsHomeFrameDef, GpsHomeFrameDefBuilder};
-use crate::frame::main::{MainFrameDef, MainFrameDefBuilder};
+use crate::frame::main::{MainFrameDef, MainFrameDefBuilder, UnitHeaders};
 use crate::frame::slow::{SlowFrameDef, SlowFrameDefBuilder};
 use crate::frame::{is_frame_def_header, parse_frame_def_header, DataFrameKind};
 use crate::parser::{InternalError, InternalResult};
 use crate::predictor::Predictor;
+use crate::units::RateCurve;
 use crate::{Reader, Unit};
 
 pub type ParseResult<T> = Result<T, ParseError>;
@@ -81,11 +83,19 @@ pub struct Headers<'data> {
     pub firmware_revision: &'data str,
     /// The firmware that wrote the log.
     pub firmware_kind: FirmwareKind,
+    /// The firmware version, if it could be parsed from `Firmware revision`.
+    pub firmware_version: Option<FirmwareVersion<'data>>,
     pub board_info: Option<&'data str>,
     pub craft_name: Option<&'data str>,
+    /// What is logged in the `debug[n]` main frame fields.
+    pub debug_mode: Option<DebugMode>,
 
     /// The battery voltage measured at arm.
     pub(crate) vbat_reference: Option<u16>,
+    /// Calibration for vbat ADC readings logged by older firmware.
+    pub(crate) vbat_calibration: Option<VbatCalibration>,
+    /// Calibration for amperage ADC readings logged by older firmware.
+    pub(crate) current_calibration: Option<CurrentCalibration>,
     /// Calibration for the accelerometer.
     pub(crate) acceleration_1g: Option<u16>,
     /// Calibration for the gyro in radians / second.
@@ -93,9 +103,22 @@ pub struct Headers<'data> {
 
     pub(crate) min_throttle: Option<u16>,
     pub(crate) motor_output_range: Option<MotorOutputRange>,
+    pub(crate) motor_poles: Option<u8>,
 
-    /// Any unknown headers with unparsed values
+    /// The number of loop iterations between intra frames.
+    pub(crate) intra_interval: Option<u16>,
+    /// The fraction of loop iterations logged as inter frames.
+    pub(crate) inter_interval: Option<InterInterval>,
+
+    pub(crate) config: Config,
+
+    /// Any unknown headers with unparsed values. This also keeps the raw values
+    /// of the headers read into [`Headers::config`].
     pub unknown: HashMap<&'data str, &'data str>,
+
+    /// The complete header section, including the product line.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(crate) raw: &'data [u8],
 }
 
 impl<'data> Headers<'data> {
@@ -106,9 +129,22 @@ impl<'data> Headers<'data> {
     ///
     /// **Note:** This assumes that `data` is aligned to the start of a log.
     pub fn parse(data: &mut Reader<'data>) -> ParseResult<Self> {
+        Self::parse_impl(data, false)
+    }
+
+    /// Like [`Headers::parse`], but logs from an unknown firmware are decoded
+    /// as if they were written by Betaflight instead of returning
+    /// [`ParseError::UnknownFirmware`].
+    pub fn parse_lenient(data: &mut Reader<'data>) -> ParseResult<Self> {
+        Self::parse_impl(data, true)
+    }
+
+    fn parse_impl(data: &mut Reader<'data>, lenient: bool) -> ParseResult<Self> {
+        let start = data.get_restore_point();
+
         // Skip product header
         let product = data.read_line();
-        debug_assert_eq!(crate::MARKER.strip_suffix(&[b'\n']), product);
+        debug_assert_eq!(crate::MARKER.strip_suffix(b"\n"), product);
 
         let mut state = State::new();
 
@@ -136,7 +172,12 @@ pub fn parse(data: &mut Reader<'data>) -> ParseResult<Self> {
             }
         }
 
-        state.finish()
+        state.finish(data.bytes_since(&start), lenient)
+    }
+
+    /// Returns the tuning and filter configuration.
+    pub fn config(&self) -> &Config {
+        &self.config
     }
 
     fn validate(&self) -> ParseResult<()> {
@@ -207,6 +248,8 @@ fn validate(&self) -> ParseResult<()> {
 #[cfg_attr(feature = "serde", derive(serde::Serialize))]
 #[non_exhaustive]
 pub enum LogVersion {
+    /// Used by early Cleanflight and Baseflight logs
+    V1,
     V2,
 }
 
@@ -224,13 +267,273 @@ pub enum FirmwareKind {
     Inav,
     /// [EmuFlight](https://github.com/emuflight/EmuFlight)
     EmuFlight,
+    /// [Cleanflight](https://github.com/cleanflight/cleanflight)
+    Cleanflight,
+    /// [Rotorflight](https://github.com/rotorflight/rotorflight-firmware)
+    Rotorflight,
+    /// Any other firmware, only returned by [`Headers::parse_lenient`]. This is
+    /// decoded as if it were Betaflight.
+    Unknown,
+}
+
+/// The version of the firmware that wrote the log, parsed from the `Firmware
+/// revision` and `Firmware date` headers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct FirmwareVersion<'data> {
+    pub major: u16,
+    pub minor: u16,
+    pub patch: u16,
+    /// The build date, exactly as written in `Firmware date`.
+    pub date: Option<&'data str>,
+    /// The abbreviated hash of the commit the firmware was built from.
+    pub hash: Option<&'data str>,
+}
+
+impl<'data> FirmwareVersion<'data> {
+    /// Parses a `Firmware revision` header, eg `Betaflight 4.2.11 (948ba6339)
+    /// STM32F7X2`. A missing patch version is treated as `0`.
+    pub(crate) fn parse(revision: &'data str, date: Option<&'data str>) -> Option<Self> {
+        let mut parts = revision.split_whitespace().skip(1);
+
+        let mut numbers = parts.next()?.split('.').map(|number| {
+            let end = number
+                .find(|c: char| !c.is_ascii_digit())
+                .unwrap_or(number.len());
+            number[..end].parse().ok()
+        });
+        let major = numbers.next()??;
+        let minor = numbers.next()??;
+        let patch = numbers.next().flatten().unwrap_or(0);
+
+        let hash = parts
+            .next()
+            .and_then(|hash| hash.strip_prefix('(')?.strip_suffix(')'));
+        let date = date.map(str::trim).filter(|date| !date.is_empty());
+
+        Some(Self {
+            major,
+            minor,
+            patch,
+            date,
+            hash,
+        })
+    }
+
+    /// Returns `true` if this is at least version `major.minor.patch`.
+    pub fn is_at_least(&self, major: u16, minor: u16, patch: u16) -> bool {
+        (self.major, self.minor, self.patch) >= (major, minor, patch)
+    }
+}
+
+impl fmt::Display for FirmwareVersion<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
 }
 
-#[derive(Debug, Clone, Copy)]
+/// What is logged in the `debug[n]` main frame fields, set by the
+/// `debug_mode` header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum DebugMode {
+    /// No debug values are logged.
+    Disabled,
+    Cycletime,
+    Battery,
+    Gyro,
+    GyroFiltered,
+    GyroScaled,
+    GyroRaw,
+    GyroSample,
+    Accelerometer,
+    PidLoop,
+    RcInterpolation,
+    AngleRate,
+    EscSensor,
+    EscSensorRpm,
+    EscSensorTemperature,
+    Scheduler,
+    Stack,
+    Altitude,
+    Agl,
+    Fft,
+    FftTime,
+    FftFreq,
+    RxFrskySpi,
+    RxSfhssSpi,
+    RxSpektrumSpi,
+    DualGyroRaw,
+    DualGyroDiff,
+    DualGyroScaled,
+    Max7456Signal,
+    Max7456SpiClock,
+    Sbus,
+    Fport,
+    Rangefinder,
+    RangefinderQuality,
+    LidarTf,
+    FlowRaw,
+    Flow,
+    AdcInternal,
+    RunawayTakeoff,
+    Sdio,
+    CurrentSensor,
+    Usb,
+    SmartAudio,
+    Rth,
+    ItermRelax,
+    AcroTrainer,
+    RcSmoothing,
+    RcSmoothingRate,
+    RxSignalLoss,
+    AntiGravity,
+    DynLpf,
+    DshotRpmTelemetry,
+    DshotRpmErrors,
+    RpmFilter,
+    Erpm,
+    RpmFrequency,
+    DMin,
+    AcCorrection,
+    AcError,
+    CrsfLinkStatisticsUplink,
+    CrsfLinkStatisticsPower,
+    CrsfLinkStatisticsDown,
+    Baro,
+    GpsRescueThrottlePid,
+    DynIdle,
+    FeedforwardLimit,
+    Feedforward,
+    BlackboxOutput,
+    RxTiming,
+    DLpf,
+    Always,
+    SagCompensationVoltage,
+    Vibration,
+    Cruise,
+    RemainingFlightTime,
+    /// A debug mode that is not known for the firmware, with its raw index.
+    Unknown(u16),
+}
+
+impl DebugMode {
+    pub(crate) fn new(raw: u16, firmware: FirmwareKind) -> Self {
+        Self::mapping(firmware)
+            .get(usize::from(raw))
+            .copied()
+            .unwrap_or_else(|| {
+                tracing::debug!("unknown debug mode ({raw})");
+                Self::Unknown(raw)
+            })
+    }
+
+    /// Returns the debug modes in order, starting from index 0.
+    fn mapping(firmware: FirmwareKind) -> &'static [Self] {
+        match firmware {
+            FirmwareKind::Betaflight | FirmwareKind::Unknown => &[
+                Self::Disabled,
+                Self::Cycletime,
+                Self::Battery,
+                Self::GyroFiltered,
+                Self::Accelerometer,
+                Self::PidLoop,
+                Self::GyroScaled,
+                Self::RcInterpolation,
+                Self::AngleRate,
+                Self::EscSensor,
+                Self::Scheduler,
+                Self::Stack,
+                Self::EscSensorRpm,
+                Self::EscSensorTemperature,
+                Self::Altitude,
+                Self::Fft,
+                Self::FftTime,
+                Self::FftFreq,
+                Self::RxFrskySpi,
+                Self::RxSfhssSpi,
+                Self::GyroRaw,
+                Self::DualGyroRaw,
+                Self::DualGyroDiff,
+                Self::Max7456Signal,
+                Self::Max7456SpiClock,
+                Self::Sbus,
+                Self::Fport,
+                Self::Rangefinder,
+                Self::RangefinderQuality,
+                Self::LidarTf,
+                Self::AdcInternal,
+                Self::RunawayTakeoff,
+                Self::Sdio,
+                Self::CurrentSensor,
+                Self::Usb,
+                Self::SmartAudio,
+                Self::Rth,
+                Self::ItermRelax,
+                Self::AcroTrainer,
+                Self::RcSmoothing,
+                Self::RxSignalLoss,
+                Self::RcSmoothingRate,
+                Self::AntiGravity,
+                Self::DynLpf,
+                Self::RxSpektrumSpi,
+                Self::DshotRpmTelemetry,
+                Self::RpmFilter,
+                Self::DMin,
+                Self::AcCorrection,
+                Self::AcError,
+                Self::DualGyroScaled,
+                Self::DshotRpmErrors,
+                Self::CrsfLinkStatisticsUplink,
+                Self::CrsfLinkStatisticsPower,
+                Self::CrsfLinkStatisticsDown,
+                Self::Baro,
+                Self::GpsRescueThrottlePid,
+                Self::DynIdle,
+                Self::FeedforwardLimit,
+                Self::Feedforward,
+                Self::BlackboxOutput,
+                Self::GyroSample,
+                Self::RxTiming,
+                Self::DLpf,
+            ],
+            FirmwareKind::Inav => &[
+                Self::Disabled,
+                Self::Gyro,
+                Self::Agl,
+                Self::FlowRaw,
+                Self::Flow,
+                Self::Sbus,
+                Self::Fport,
+                Self::Always,
+                Self::SagCompensationVoltage,
+                Self::Vibration,
+                Self::Cruise,
+                Self::RemainingFlightTime,
+                Self::SmartAudio,
+                Self::Accelerometer,
+                Self::Erpm,
+                Self::RpmFilter,
+                Self::RpmFrequency,
+            ],
+            // These have diverged from Betaflight, so only `Disabled` is known
+            FirmwareKind::EmuFlight | FirmwareKind::Cleanflight | FirmwareKind::Rotorflight => {
+                &[Self::Disabled]
+            }
+        }
+    }
+}
+
+impl fmt::Display for DebugMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self, f)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub(crate) struct MotorOutputRange {
     pub(crate) min: u16,
-    #[allow(dead_code)]
     pub(crate) max: u16,
 }
 
@@ -244,6 +547,69 @@ pub(crate) fn from_str(s: &str) -> Option<Self> {
     }
 }
 
+/// Converts vbat ADC readings into volts. (`vbatscale` or `vbat_scale`
+/// header)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub(crate) struct VbatCalibration {
+    /// Voltage divider ratio, multiplied by 10.
+    pub(crate) scale: u16,
+}
+
+impl Default for VbatCalibration {
+    fn default() -> Self {
+        Self { scale: 110 }
+    }
+}
+
+/// Converts amperage ADC readings into amps. (`currentMeter` or
+/// `currentSensor` header)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub(crate) struct CurrentCalibration {
+    /// Sensor output at 0A, in millivolts.
+    pub(crate) offset: i32,
+    /// Sensor output in millivolts per 10A.
+    pub(crate) scale: i32,
+}
+
+impl CurrentCalibration {
+    pub(crate) fn from_str(s: &str) -> Option<Self> {
+        let (offset, scale) = s.split_once(',')?;
+        let offset = offset.trim().parse().ok()?;
+        let scale = scale.trim().parse().ok()?;
+        Some(Self { offset, scale })
+    }
+}
+
+impl Default for CurrentCalibration {
+    fn default() -> Self {
+        Self {
+            offset: 0,
+            scale: 400,
+        }
+    }
+}
+
+/// The fraction of loop iterations that are logged. (`P interval` header)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub(crate) struct InterInterval {
+    pub(crate) num: u16,
+    pub(crate) denom: u16,
+}
+
+impl InterInterval {
+    /// Parses either the `num/denom` format or, as written by Betaflight 4.x,
+    /// a plain denominator.
+    pub(crate) fn from_str(s: &str) -> Option<Self> {
+        let (num, denom) = s.split_once('/').unwrap_or(("1", s));
+        let num = num.trim().parse().ok()?;
+        let denom = denom.trim().parse().ok()?;
+        Some(Self { num, denom })
+    }
+}
+
 #[derive(Debug)]
 struct State<'data> {
     version: Option<LogVersion>,
@@ -254,16 +620,26 @@ struct State<'data> {
 
     firmware_revision: Option<&'data str>,
     firmware_kind: Option<&'data str>,
+    firmware_date: Option<&'data str>,
     board_info: Option<&'data str>,
     craft_name: Option<&'data str>,
+    debug_mode: Option<u16>,
 
     vbat_reference: Option<u16>,
+    vbat_calibration: Option<VbatCalibration>,
+    current_calibration: Option<CurrentCalibration>,
     acceleration_1g: Option<u16>,
     gyro_scale: Option<f32>,
 
     min_throttle: Option<u16>,
     motor_output_range: Option<MotorOutputRange>,
+    motor_poles: Option<u8>,
 
+    intra_interval: Option<u16>,
+    inter_interval: Option<InterInterval>,
+    inter_ratio: Option<u16>,
+
+    config: ConfigBuilder<'data>,
     unknown: HashMap<&'data str, &'data str>,
 }
 
@@ -278,16 +654,26 @@ fn new() -> Self {
 
             firmware_revision: None,
             firmware_kind: None,
+            firmware_date: None,
             board_info: None,
             craft_name: None,
+            debug_mode: None,
 
             vbat_reference: None,
+            vbat_calibration: None,
+            current_calibration: None,
             acceleration_1g: None,
             gyro_scale: None,
 
             min_throttle: None,
             motor_output_range: None,
+            motor_poles: None,
+
+            intra_interval: None,
+            inter_interval: None,
+            inter_ratio: None,
 
+            config: ConfigBuilder::default(),
             unknown: HashMap::new(),
         }
     }
@@ -298,16 +684,21 @@ fn update(&mut self, header: &'data str, value: &'data str) -> bool {
         (|| -> Result<(), ()> {
             match header {
                 "Data version" => {
-                    if value == "2" {
-                        self.version = Some(LogVersion::V2);
-                    } else {
-                        return Err(());
-                    }
+                    self.version = Some(match value {
+                        "1" => LogVersion::V1,
+                        "2" => LogVersion::V2,
+                        _ => return Err(()),
+                    });
                 }
                 "Firmware revision" => self.firmware_revision = Some(value),
                 "Firmware type" => self.firmware_kind = Some(value),
+                "Firmware date" => self.firmware_date = Some(value),
                 "Board information" => self.board_info = Some(value),
                 "Craft name" => self.craft_name = Some(value),
+                "debug_mode" => match value.parse() {
+                    Ok(mode) => self.debug_mode = Some(mode),
+                    Err(_) => self.skip_invalid(header, value),
+                },
 
                 "vbatref" => {
                     let vbat_reference = value.parse().map_err(|_| ())?;
@@ -335,6 +726,22 @@ fn update(&mut self, header: &'data str, value: &'data str) -> bool {
                     let range = MotorOutputRange::from_str(value).ok_or(())?;
                     self.motor_output_range = Some(range);
                 }
+                "motor_poles" => match value.parse() {
+                    Ok(poles) => self.motor_poles = Some(poles),
+                    Err(_) => self.skip_invalid(header, value),
+                },
+                "I interval" => {
+                    let interval = value.parse().map_err(|_| ())?;
+                    self.intra_interval = Some(interval);
+                }
+                "P interval" => {
+                    let interval = InterInterval::from_str(value).ok_or(())?;
+                    self.inter_interval = Some(interval);
+                }
+                "P ratio" => {
+                    let ratio = value.parse().map_err(|_| ())?;
+                    self.inter_ratio = Some(ratio);
+                }
 
                 _ if is_frame_def_header(header) => {
                     let (frame_kind, property) = parse_frame_def_header(header).unwrap();
@@ -350,7 +757,21 @@ fn update(&mut self, header: &'data str, value: &'data str) -> bool {
                 }
 
                 // Legacy calibration headers
-                "vbatscale" | "vbat_scale" | "currentMeter" | "currentSensor" => {}
+                // These only affect unit conversion, so an unexpected value falls back to the
+                // default rather than rejecting the log
+                "vbatscale" | "vbat_scale" => match value.parse() {
+                    Ok(scale) => self.vbat_calibration = Some(VbatCalibration { scale }),
+                    Err(_) => self.skip_invalid(header, value),
+                },
+                "currentMeter" | "currentSensor" => match CurrentCalibration::from_str(value) {
+                    Some(calibration) => self.current_calibration = Some(calibration),
+                    None => self.skip_invalid(header, value),
+                },
+
+                // The config is parsed leniently, so keep the raw value too
+                _ if self.config.update(header, value) => {
+                    self.unknown.insert(header, value);
+                }
 
                 header => {
                     tracing::debug!("skipping unknown header: `{header}` = `{value}`");
@@ -363,7 +784,14 @@ fn update(&mut self, header: &'data str, value: &'data str) -> bool {
         .is_ok()
     }
 
-    fn finish(self) -> ParseResult<Headers<'data>> {
+    /// Keeps an optional header whose value could not be parsed in `unknown`
+    /// instead of failing the whole log.
+    fn skip_invalid(&mut self, header: &'data str, value: &'data str) {
+        tracing::warn!("ignoring invalid value for header `{header}`: `{value}`");
+        self.unknown.insert(header, value);
+    }
+
+    fn finish(self, raw: &'data [u8], lenient: bool) -> ParseResult<Headers<'data>> {
         let not_empty = |s: &&str| !s.is_empty();
 
         let firmware_revision = self.firmware_revision.ok_or(ParseError::MissingHeader)?;
@@ -374,33 +802,74 @@ fn finish(self) -> ParseResult<Headers<'data>> {
             Some("betaflight") => FirmwareKind::Betaflight,
             Some("inav") => FirmwareKind::Inav,
             Some("emuflight") => FirmwareKind::EmuFlight,
+            Some("cleanflight") => FirmwareKind::Cleanflight,
+            Some("rotorflight") => FirmwareKind::Rotorflight,
+            _ if lenient => {
+                tracing::warn!("unknown firmware `{firmware_revision}`, decoding as Betaflight");
+                FirmwareKind::Unknown
+            }
             _ => {
                 tracing::error!("Could not parse firmware revision");
                 return Err(ParseError::UnknownFirmware(firmware_revision.to_owned()));
             }
         };
 
+        let firmware_version = FirmwareVersion::parse(firmware_revision, self.firmware_date);
+        if firmware_version.is_none() {
+            tracing::debug!("could not parse firmware version from `{firmware_revision}`");
+        }
+
+        // Fall back to `P ratio` if `P interval` is missing, since
+        // `P ratio = I interval / P interval`
+        let inter_interval = self.inter_interval.or_else(|| {
+            let intra = self.intra_interval?;
+            let ratio = self.inter_ratio.filter(|&ratio| ratio > 0)?;
+            Some(InterInterval {
+                num: 1,
+                denom: intra / ratio,
+            })
+        });
+
+        let config = self.config.build(firmware_kind);
+        let units = UnitHeaders {
+            debug_mode: self
+                .debug_mode
+                .map(|mode| DebugMode::new(mode, firmware_kind)),
+            has_motor_output: self.motor_output_range.is_some(),
+            has_rates: RateCurve::new(&config.rates, firmware_kind).is_some(),
+        };
+
         // TODO: log where each error comes from
         let headers = Headers {
             version: self.version.ok_or(ParseError::MissingHeader)?,
-            main_frame_def: self.main_frames.parse()?,
+            main_frame_def: self.main_frames.parse(&units)?,
             slow_frame_def: self.slow_frames.parse()?,
             gps_frame_def: self.gps_frames.parse()?,
             gps_home_frame_def: self.gps_home_frames.parse()?,
 
             firmware_revision,
             firmware_kind,
+            firmware_version,
             board_info: self.board_info.map(str::trim).filter(not_empty),
             craft_name: self.craft_name.map(str::trim).filter(not_empty),
+            debug_mode: units.debug_mode,
 
             vbat_reference: self.vbat_reference,
+            vbat_calibration: self.vbat_calibration,
+            current_calibration: self.current_calibration,
             acceleration_1g: self.acceleration_1g,
             gyro_scale: self.gyro_scale,
 
             min_throttle: self.min_throttle,
             motor_output_range: self.motor_output_range,
+            motor_poles: self.motor_poles,
 
+            intra_interval: self.intra_interval,
+            inter_interval,
+
+            config,
             unknown: self.unknown,
+            raw,
         };
 
         headers.validate()?;
@@ -430,8 +899,139 @@ fn parse_header<'data>(bytes: &mut Reader<'data>) -> InternalResult<(&'data str,
 
 #[cfg(test)]
 mod tests {
+    use test_case::case;
+
     use super::*;
 
+    #[case("Betaflight 4.2.11 (948ba6339) STM32F7X2", Some((4, 2, 11, Some("948ba6339"))); "betaflight")]
+    #[case("INAV 5.0.0 (6e52a8a7) MATEKF722SE", Some((5, 0, 0, Some("6e52a8a7"))); "inav")]
+    #[case("Betaflight 4.3.0-RC1 (1234567) STM32F405", Some((4, 3, 0, Some("1234567"))); "release candidate")]
+    #[case("EmuFlight 0.4", Some((0, 4, 0, None)); "missing patch")]
+    #[case("Betaflight 2025.12.1 (abcdef0) STM32H743", Some((2025, 12, 1, Some("abcdef0"))); "calendar version")]
+    #[case("Betaflight", None; "missing version")]
+    #[case("Betaflight master (1234567) STM32F405", None; "not a version")]
+    fn firmware_version(revision: &str, expected: Option<(u16, u16, u16, Option<&str>)>) {
+        let version = FirmwareVersion::parse(revision, Some(" Nov  9 2021 20:29:32"));
+        let expected = expected.map(|(major, minor, patch, hash)| FirmwareVersion {
+            major,
+            minor,
+            patch,
+            date: Some("Nov  9 2021 20:29:32"),
+            hash,
+        });
+        assert_eq!(expected, version);
+    }
+
+    #[case(FirmwareKind::Betaflight, 6 => DebugMode::GyroScaled; "betaflight gyro scaled")]
+    #[case(FirmwareKind::Betaflight, 46 => DebugMode::RpmFilter; "betaflight rpm filter")]
+    #[case(FirmwareKind::Betaflight, 63 => DebugMode::DLpf; "betaflight d lpf")]
+    #[case(FirmwareKind::Inav, 15 => DebugMode::RpmFilter; "inav rpm filter")]
+    #[case(FirmwareKind::EmuFlight, 6 => DebugMode::Unknown(6); "emuflight")]
+    #[case(FirmwareKind::Betaflight, 999 => DebugMode::Unknown(999); "unknown")]
+    fn debug_mode(firmware: FirmwareKind, raw: u16) -> DebugMode {
+        DebugMode::new(raw, firmware)
+    }
+
+    #[case("Betaflight 4.2.11 (948ba6339) STM32F7X2", FirmwareKind::Betaflight; "betaflight")]
+    #[case("Cleanflight 2.5.0 (8a6f5a9f1) SPRACINGF3", FirmwareKind::Cleanflight; "cleanflight")]
+    #[case("Rotorflight 4.3.0 (20a2ed07f) STM32F7X2", FirmwareKind::Rotorflight; "rotorflight")]
+    fn firmware_kind(revision: &str, expected: FirmwareKind) {
+        let log = crate::utils::log_with_headers(revision, &[]);
+        let headers = Headers::parse(&mut Reader::new(&log)).unwrap();
+        assert_eq!(expected, headers.firmware_kind);
+    }
+
+    #[test]
+    fn unknown_firmware() {
+        let log = crate::utils::log_with_headers("Baseflight 2015.08.27 (abcdef0) NAZE", &[]);
+
+        let err = Headers::parse(&mut Reader::new(&log)).unwrap_err();
+        assert!(matches!(err, ParseError::UnknownFirmware(_)));
+
+        let headers = Headers::parse_lenient(&mut Reader::new(&log)).unwrap();
+        assert_eq!(FirmwareKind::Unknown, headers.firmware_kind);
+    }
+
+    #[test]
+    fn firmware_version_at_least() {
+        let version = FirmwareVersion::parse("Betaflight 3.1.7", None).unwrap();
+        assert!(version.is_at_least(3, 1, 0));
+        assert!(version.is_at_least(3, 1, 7));
+        assert!(!version.is_at_least(3, 2, 0));
+        assert!(!version.is_at_least(4, 0, 0));
+    }
+
+    #[case("0,400", Some((0, 400)); "default")]
+    #[case("-12, 35", Some((-12, 35)); "negative offset")]
+    #[case("400", None; "missing offset")]
+    #[case("0,x", None; "invalid")]
+    fn current_calibration(s: &str, expected: Option<(i32, i32)>) {
+        let expected = expected.map(|(offset, scale)| CurrentCalibration { offset, scale });
+        assert_eq!(expected, CurrentCalibration::from_str(s));
+    }
+
+    #[test]
+    fn inter_interval_fraction() {
+        let interval = InterInterval::from_str("1/2").unwrap();
+        assert_eq!(InterInterval { num: 1, denom: 2 }, interval);
+    }
+
+    #[test]
+    fn inter_interval_denominator() {
+        let interval = InterInterval::from_str("16").unwrap();
+        assert_eq!(InterInterval { num: 1, denom: 16 }, interval);
+    }
+
+    #[test]
+    fn inter_interval_invalid() {
+        assert_eq!(None, InterInterval::from_str("1/"));
+    }
+
+    #[test]
+    fn wide_vbat_scale() {
+        let mut state = State::new();
+        assert!(state.update("vbat_scale", "1100"));
+        assert_eq!(
+            Some(VbatCalibration { scale: 1100 }),
+            state.vbat_calibration
+        );
+    }
+
+    #[case("vbatscale", "-1"; "vbat scale")]
+    #[case("currentSensor", "400"; "current sensor")]
+    #[case("debug_mode", "GYRO_SCALED"; "debug mode")]
+    #[case("motor_poles", "x"; "motor poles")]
+    fn invalid_optional_header(header: &str, value: &str) {
+        let mut state = State::new();
+        assert!(state.update(header, value));
+        assert_eq!(None, state.vbat_calibration);
+        assert_eq!(None, state.current_calibration);
+        assert_eq!(None, state.debug_mode);
+        assert_eq!(None, state.motor_poles);
+        assert_eq!(Some(&value), state.unknown.get(header));
+    }
+
+    #[case("rollPID", "50,102,36"; "valid")]
+    #[case("looptime", "x"; "invalid")]
+    fn config_header_kept(header: &str, value: &str) {
+        let mut state = State::new();
+        assert!(state.update(header, value));
+        assert_eq!(Some(&value), state.unknown.get(header));
+    }
+
+    #[test]
+    fn data_version() {
+        let mut state = State::new();
+
+        assert!(state.update("Data version", "1"));
+        assert_eq!(Some(LogVersion::V1), state.version);
+
+        assert!(state.update("Data version", "2"));
+        assert_eq!(Some(LogVersion::V2), state.version);
+
+        assert!(!state.update("Data version", "3"));
+    }
+
     #[test]
     #[should_panic(expected = "Retry")]
     fn invalid_utf8() {
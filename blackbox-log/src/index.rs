@@ -0,0 +1,232 @@
+//! Seeking within the data section of a log.
+//!
+//! Decoding a main frame requires the previous frames back to the last intra
+//! (`I`) frame, so those are the only places [`DataParser`] can start from. An
+//! [`Index`] records where each valid intra frame starts, which allows jumping
+//! directly to a given time or iteration:
+//!
+//! ```
+//! use blackbox_log::index::Index;
+//! use blackbox_log::prelude::*;
+//!
+//! # let file = include_bytes!("../tests/logs/error-recovery.bbl");
+//! let file = blackbox_log::File::new(file);
+//! let mut reader = file.get_reader(0);
+//! let headers = Headers::parse(&mut reader)?;
+//!
+//! let index = Index::new(reader.clone(), &headers);
+//! let mut parser = DataParser::new(reader, &headers);
+//!
+//! if let Some(entry) = index.find_by_time(5_000_000) {
+//!     parser.seek(entry);
+//! }
+//!
+//! while let Some(event) = parser.next() {
+//!     // ...
+//! }
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+use alloc::vec::Vec;
+
+use crate::data::ParseEvent;
+use crate::frame::GpsPosition;
+use crate::{DataParser, Headers, Reader};
+
+/// The location of every valid intra frame in a log.
+#[derive(Debug, Clone, Default)]
+pub struct Index {
+    entries: Vec<IndexEntry>,
+}
+
+/// A single intra frame in an [`Index`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IndexEntry {
+    /// Offset of the start of the frame from the start of the log.
+    pub offset: usize,
+    /// The value of the `loopIteration` field.
+    pub iteration: u32,
+    /// The value of the `time` field in microseconds.
+    pub time: u64,
+    pub(crate) gps_home: Option<GpsPosition>,
+}
+
+impl Index {
+    /// Builds an index of the data section read by `data`.
+    ///
+    /// This decodes the whole data section to ensure only frames that would
+    /// also be returned by a [`DataParser`] are included.
+    pub fn new(data: Reader<'_>, headers: &Headers<'_>) -> Self {
+        let mut parser = DataParser::new(data, headers);
+        let mut entries = Vec::new();
+
+        while let Some(event) = parser.next() {
+            let ParseEvent::Main(frame) = event else {
+                continue;
+            };
+
+            if frame.raw.intra {
+                let (iteration, time) = (frame.raw.iteration, frame.raw.time);
+
+                entries.push(IndexEntry {
+                    offset: parser.frame_offset(),
+                    iteration,
+                    time,
+                    gps_home: parser.gps_home(),
+                });
+            }
+        }
+
+        Self { entries }
+    }
+
+    /// Returns all indexed frames, in the order they appear in the log.
+    pub fn entries(&self) -> &[IndexEntry] {
+        &self.entries
+    }
+
+    /// Returns the last indexed frame at or before `time`.
+    ///
+    /// This assumes the times are increasing throughout the log.
+    pub fn find_by_time(&self, time: u64) -> Option<&IndexEntry> {
+        let end = self.entries.partition_point(|entry| entry.time <= time);
+        end.checked_sub(1).map(|i| &self.entries[i])
+    }
+
+    /// Returns the last indexed frame at or before `iteration`.
+    ///
+    /// This assumes the iterations are increasing throughout the log.
+    pub fn find_by_iteration(&self, iteration: u32) -> Option<&IndexEntry> {
+        let end = self
+            .entries
+            .partition_point(|entry| entry.iteration <= iteration);
+        end.checked_sub(1).map(|i| &self.entries[i])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+
+    use test_case::case;
+
+    use super::*;
+    use crate::utils::as_u32;
+    use crate::writer::Writer;
+    use crate::File;
+
+    #[derive(Debug, PartialEq)]
+    enum Frame {
+        Main(u32, u64, Vec<u32>),
+        Gps(u64, Vec<u32>),
+    }
+
+    fn parse_from(
+        data: Reader<'_>,
+        headers: &Headers<'_>,
+        entry: Option<&IndexEntry>,
+    ) -> Vec<(usize, Frame)> {
+        let mut parser = DataParser::new(data, headers);
+        if let Some(entry) = entry {
+            parser.seek(entry);
+        }
+
+        let mut frames = Vec::new();
+        while let Some(event) = parser.next() {
+            let frame = match event {
+                ParseEvent::Main(main) => {
+                    Frame::Main(main.raw.iteration, main.raw.time, main.raw.values.clone())
+                }
+                ParseEvent::Gps(gps) => Frame::Gps(gps.raw.time, gps.raw.values),
+                ParseEvent::Event(_) | ParseEvent::Slow(_) => continue,
+            };
+
+            frames.push((parser.frame_offset(), frame));
+        }
+
+        frames
+    }
+
+    /// Only includes a GPS home frame before the first intra frame
+    fn gps_log() -> Vec<u8> {
+        let log = include_bytes!("../tests/logs/gps.bbl");
+        let headers = Headers::parse(&mut Reader::new(log)).unwrap();
+
+        let mut writer = Writer::new(&headers);
+        writer.write_gps_home(100, -100).unwrap();
+
+        for i in 0..3 {
+            let time = 1_000 * u64::from(i);
+            writer.write_intra(32 * i, time, &[1]).unwrap();
+            writer.write_inter(32 * i + 2, time + 10, &[2]).unwrap();
+            writer
+                .write_gps(time + 15, &[8, 101, as_u32(-99), 5, 0, 0])
+                .unwrap();
+        }
+
+        writer.into_bytes()
+    }
+
+    #[case(include_bytes!("../tests/logs/error-recovery.bbl"); "error recovery")]
+    #[case(&gps_log(); "gps")]
+    fn seek(log: &[u8]) {
+        let file = File::new(log);
+        let mut reader = file.get_reader(0);
+        let headers = Headers::parse(&mut reader).unwrap();
+
+        let index = Index::new(reader.clone(), &headers);
+        assert!(index.entries().len() > 1);
+
+        let all = parse_from(reader.clone(), &headers, None);
+
+        for entry in index.entries() {
+            assert_eq!(
+                Some(&b'I'),
+                file.get_reader(0).read_n_bytes(entry.offset + 1).last()
+            );
+
+            let start = all
+                .iter()
+                .position(|(offset, _)| *offset == entry.offset)
+                .unwrap();
+            assert_eq!(
+                all[start..],
+                parse_from(reader.clone(), &headers, Some(entry))
+            );
+        }
+    }
+
+    fn entry(iteration: u32, time: u64) -> IndexEntry {
+        IndexEntry {
+            offset: 0,
+            iteration,
+            time,
+            gps_home: None,
+        }
+    }
+
+    #[case(0 => None; "before start")]
+    #[case(100 => Some(100); "exact")]
+    #[case(150 => Some(100); "between")]
+    #[case(1000 => Some(300); "after end")]
+    fn find_by_time(time: u64) -> Option<u64> {
+        let index = Index {
+            entries: [100, 200, 300].map(|t| entry(0, t)).to_vec(),
+        };
+
+        index.find_by_time(time).map(|entry| entry.time)
+    }
+
+    #[case(31 => None; "before start")]
+    #[case(64 => Some(64); "exact")]
+    #[case(95 => Some(64); "between")]
+    fn find_by_iteration(iteration: u32) -> Option<u32> {
+        let index = Index {
+            entries: [32, 64, 96].map(|i| entry(i, 0)).to_vec(),
+        };
+
+        index
+            .find_by_iteration(iteration)
+            .map(|entry| entry.iteration)
+    }
+}
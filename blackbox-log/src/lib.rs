@@ -73,8 +73,11 @@
 //!
 //! # Features
 //!
-//! - `std`: **Enabled** by default. Currently, this only implements
-//!   [`std::error::Error`] for [`HeadersParseError`].
+//! - `std`: **Enabled** by default. This implements [`std::error::Error`] for
+//!   [`HeadersParseError`] and enables the [`stream`] module to parse logs
+//!   from any [`std::io::Read`].
+//! - `rayon`: **Disabled** by default. This enables the [`parallel`] module to
+//!   decode a single log using multiple threads. Implies `std`.
 //! - `serde`: **Disabled** by default. This allows serializing parsed logs
 //!   using `serde`. **Note:** This is only used for snapshot testing and is not
 //!   yet intended for public use.
@@ -88,19 +91,26 @@
 #[macro_use]
 mod utils;
 
+pub mod config;
 pub mod data;
 pub mod event;
 mod file;
 mod filter;
 pub mod frame;
 pub mod headers;
+pub mod index;
+#[cfg(feature = "rayon")]
+pub mod parallel;
 mod parser;
 mod predictor;
 pub mod prelude;
 mod reader;
+#[cfg(feature = "std")]
+pub mod stream;
 pub mod units;
+pub mod writer;
 
-pub use self::data::{DataParser, ParseEvent};
+pub use self::data::{DataParser, ParseEvent, StrictDataParser};
 pub use self::file::File;
 pub use self::filter::FieldFilter;
 pub use self::frame::{Unit, Value};
@@ -0,0 +1,499 @@
+//! Decoding of a single log using multiple threads.
+//!
+//! Intra (`I`) frames do not depend on any earlier frames, so the data section
+//! can be split at intra frames and each chunk decoded independently. The
+//! chunks are then joined back together in order, with the GPS home position
+//! carried across the boundaries.
+//!
+//! The result is identical to decoding the whole log using a
+//! [`DataParser`](crate::DataParser), which means any state derived from
+//! earlier frames, like the most recent slow frame, can be tracked while
+//! iterating over [`DecodedData::events`].
+//!
+//! # Example
+//!
+//! ```
+//! use blackbox_log::prelude::*;
+//!
+//! # let file = include_bytes!("../tests/logs/error-recovery.bbl");
+//! let file = blackbox_log::File::new(file);
+//! let mut reader = file.get_reader(0);
+//! let headers = Headers::parse(&mut reader)?;
+//!
+//! let data = blackbox_log::parallel::decode(reader, &headers);
+//! for event in data.events() {
+//!     if let ParseEvent::Main(main) = event {
+//!         for (value, name) in main.iter().zip(headers.main_frame_def.iter_names()) {
+//!             println!("{name}: {value:?}");
+//!         }
+//!     }
+//! }
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+use alloc::vec::Vec;
+use core::mem;
+
+use rayon::prelude::*;
+
+use crate::data::{is_frame_kind, FrameCounts, InternalFrame, ParseEvent, ParserState, Stats};
+use crate::event::Event;
+use crate::frame::gps::GpsFrame;
+use crate::frame::main::MainFrame;
+use crate::frame::slow::SlowFrame;
+use crate::frame::{DataFrameKind, FrameKind};
+use crate::parser::{expand_time, truncate_time};
+use crate::{Headers, Reader};
+
+/// Chunks will not be split any smaller than this many bytes.
+const MIN_CHUNK_SIZE: usize = 64 * 1024;
+
+/// Number of frames that must be valid, starting with an intra frame, for it
+/// to be used as the start of a chunk.
+const VALIDATE_FRAMES: usize = 3;
+
+/// A data section decoded by [`decode`].
+#[derive(Debug)]
+pub struct DecodedData<'data, 'headers> {
+    headers: &'headers Headers<'data>,
+    frames: Vec<InternalFrame>,
+    stats: Stats,
+}
+
+impl<'data, 'headers> DecodedData<'data, 'headers> {
+    /// Returns the stats for the whole data section.
+    pub fn stats(&self) -> &Stats {
+        &self.stats
+    }
+
+    /// Iterates over the decoded frames in the order they appear in the log,
+    /// exactly as they would be returned by
+    /// [`DataParser::next`](crate::DataParser::next).
+    pub fn events(&self) -> impl Iterator<Item = ParseEvent<'data, 'headers, '_>> + '_ {
+        self.frames.iter().map(|frame| match frame {
+            InternalFrame::Event(event) => ParseEvent::Event(event.clone()),
+            InternalFrame::Main(main) => ParseEvent::Main(MainFrame::new(self.headers, main)),
+            InternalFrame::Slow(slow) => {
+                ParseEvent::Slow(SlowFrame::new(self.headers, slow.clone()))
+            }
+            InternalFrame::Gps(gps) => ParseEvent::Gps(GpsFrame::new(self.headers, gps.clone())),
+            InternalFrame::GpsHome(_) => unreachable!("GPS home frames are not stored"),
+        })
+    }
+}
+
+/// Decodes the data section read by `data` using the global rayon thread
+/// pool.
+pub fn decode<'data, 'headers>(
+    data: Reader<'data>,
+    headers: &'headers Headers<'data>,
+) -> DecodedData<'data, 'headers> {
+    decode_impl(data, headers, false)
+}
+
+/// Like [`decode`], but also records the location of each corrupted frame in
+/// [`Stats::corruptions`], the same as
+/// [`DataParser::record_corruptions`](crate::DataParser::record_corruptions).
+pub fn decode_recording_corruptions<'data, 'headers>(
+    data: Reader<'data>,
+    headers: &'headers Headers<'data>,
+) -> DecodedData<'data, 'headers> {
+    decode_impl(data, headers, true)
+}
+
+fn decode_impl<'data, 'headers>(
+    data: Reader<'data>,
+    headers: &'headers Headers<'data>,
+    record_corruptions: bool,
+) -> DecodedData<'data, 'headers> {
+    let chunks = (data.remaining() / MIN_CHUNK_SIZE).clamp(1, rayon::current_num_threads() * 4);
+    let chunk_size = data.remaining() / chunks;
+    decode_with_chunk_size(data, headers, chunk_size.max(1), record_corruptions)
+}
+
+fn decode_with_chunk_size<'data, 'headers>(
+    data: Reader<'data>,
+    headers: &'headers Headers<'data>,
+    chunk_size: usize,
+    record_corruptions: bool,
+) -> DecodedData<'data, 'headers> {
+    let start = data.position();
+    let end = start + data.remaining();
+
+    let mut starts = (start + chunk_size..end)
+        .step_by(chunk_size)
+        .collect::<Vec<_>>()
+        .into_par_iter()
+        .filter_map(|target| find_intra(data.clone(), headers, target))
+        .collect::<Vec<_>>();
+    starts.insert(0, start);
+    starts.dedup();
+
+    decode_chunks(data, headers, &starts, record_corruptions)
+}
+
+/// Decodes chunks beginning at each of `starts` and joins them together.
+///
+/// Each start must be either an intra frame or not the start of any frame.
+fn decode_chunks<'data, 'headers>(
+    data: Reader<'data>,
+    headers: &'headers Headers<'data>,
+    starts: &[usize],
+    record_corruptions: bool,
+) -> DecodedData<'data, 'headers> {
+    let chunks = (0..starts.len())
+        .into_par_iter()
+        .map(|i| {
+            let bound = starts.get(i + 1).copied().unwrap_or(usize::MAX);
+            decode_chunk(
+                data.clone(),
+                headers,
+                starts[i],
+                bound,
+                ParserState::default(),
+                record_corruptions,
+            )
+        })
+        .collect::<Vec<_>>();
+
+    let mut decoded = DecodedData {
+        headers,
+        frames: Vec::new(),
+        stats: Stats {
+            corruptions: record_corruptions.then(Vec::new),
+            ..Stats::default()
+        },
+    };
+
+    let mut state = ParserState::default();
+    let mut position = starts[0];
+    let mut gps_home = None;
+    for mut chunk in chunks {
+        // The previous chunk ended somewhere other than the start of this one, so
+        // this chunk's start was not a frame the sequential parser would find
+        if chunk.start != position {
+            tracing::debug!(
+                "chunk at {} did not line up, redecoding from {position}",
+                chunk.start
+            );
+            chunk = decode_chunk(
+                data.clone(),
+                headers,
+                position,
+                chunk.bound,
+                mem::take(&mut state),
+                record_corruptions,
+            );
+        }
+
+        if let (Some(home), Some(def)) = (gps_home, &headers.gps_frame_def) {
+            for &i in &chunk.missing_gps_home {
+                if let InternalFrame::Gps(ref mut gps) = chunk.frames[i] {
+                    gps.apply_home(def, home);
+                }
+            }
+        }
+
+        gps_home = chunk.state.gps_home().or(gps_home);
+        add_stats(&mut decoded.stats, &chunk.state.stats);
+        decoded.frames.append(&mut chunk.frames);
+
+        match chunk.end {
+            Some(end) if !chunk.state.done => position = end,
+            _ => break,
+        }
+
+        state = chunk.state;
+    }
+
+    expand_times(&mut decoded.frames);
+    decoded
+}
+
+/// Re-expands every timestamp in order, since chunks are decoded without
+/// knowing the time of any frames before them.
+fn expand_times(frames: &mut [InternalFrame]) {
+    let mut last_time = None;
+    let mut last_main_time = None;
+    let expand = |time: &mut u64, last_time: Option<u64>| {
+        *time = expand_time(truncate_time(*time), last_time);
+    };
+
+    for frame in frames {
+        match frame {
+            InternalFrame::Main(main) => {
+                expand(&mut main.time, last_time);
+                last_time = Some(main.time);
+                last_main_time = last_time;
+            }
+            InternalFrame::Event(event) => {
+                if event.time.is_some() {
+                    event.time = last_main_time;
+                }
+
+                match &mut event.event {
+                    Event::Resume { time, .. } => {
+                        expand(time, last_time);
+                        last_time = Some(*time);
+                    }
+                    Event::SyncBeep(time) => expand(time, last_time),
+                    _ => {}
+                }
+            }
+            InternalFrame::Gps(gps) => expand(&mut gps.time, last_time),
+            InternalFrame::Slow(_) | InternalFrame::GpsHome(_) => {}
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Chunk {
+    start: usize,
+    bound: usize,
+    frames: Vec<InternalFrame>,
+    /// Indices of GPS frames decoded before any GPS home frame in this chunk
+    missing_gps_home: Vec<usize>,
+    /// Offset of the first frame at or after `bound`, or `None` if the parser
+    /// stopped before reaching it
+    end: Option<usize>,
+    state: ParserState,
+}
+
+/// Decodes all frames starting before `bound`.
+fn decode_chunk(
+    mut data: Reader,
+    headers: &Headers,
+    start: usize,
+    bound: usize,
+    mut state: ParserState,
+    record_corruptions: bool,
+) -> Chunk {
+    data.seek(start);
+    state.stats = Stats::default();
+    if record_corruptions {
+        state.record_corruptions();
+    }
+
+    let mut frames = Vec::new();
+    let mut missing_gps_home = Vec::new();
+
+    let end = loop {
+        if state.done {
+            break Some(data.position());
+        }
+
+        let Some((offset, frame)) = state.next_frame(&mut data, headers) else {
+            break None;
+        };
+
+        if offset >= bound {
+            break Some(offset);
+        }
+
+        if matches!(frame, InternalFrame::Gps(_)) && state.gps_home().is_none() {
+            missing_gps_home.push(frames.len());
+        }
+
+        frames.push(frame.clone());
+        state.emit(headers, frame);
+    };
+
+    Chunk {
+        start,
+        bound,
+        frames,
+        missing_gps_home,
+        end,
+        state,
+    }
+}
+
+/// Finds the first intra frame at or after `from` that is followed by valid
+/// frames.
+fn find_intra(mut data: Reader, headers: &Headers, from: usize) -> Option<usize> {
+    let intra = u8::from(FrameKind::Data(DataFrameKind::Intra));
+
+    data.seek(from);
+    while data.skip_until_any(&[intra]) {
+        let offset = data.position();
+        if is_valid_start(data.clone(), headers) {
+            return Some(offset);
+        }
+
+        data.seek(offset + 1);
+    }
+
+    None
+}
+
+fn is_valid_start(mut data: Reader, headers: &Headers) -> bool {
+    let mut state = ParserState::default();
+
+    for i in 0..VALIDATE_FRAMES {
+        let offset = data.position();
+        let Some(byte) = data.read_u8() else {
+            // Reaching the end of the log after the intra frame is fine
+            return i > 0;
+        };
+
+        let Some(kind) = FrameKind::from_byte(byte) else {
+            return false;
+        };
+
+        match state.parse_frame(&mut data, headers, offset, kind) {
+            Ok(InternalFrame::GpsHome(home)) if data.peek().map_or(true, is_frame_kind) => {
+                state.update_gps_home(home);
+            }
+            Ok(frame) if data.peek().map_or(true, is_frame_kind) => {
+                state.emit(headers, frame);
+            }
+            _ => return false,
+        }
+    }
+
+    true
+}
+
+fn add_stats(total: &mut Stats, chunk: &Stats) {
+    add_counts(&mut total.counts, &chunk.counts);
+    add_counts(&mut total.corrupted, &chunk.corrupted);
+    total.skipped_bytes += chunk.skipped_bytes;
+    if let (Some(total), Some(chunk)) = (&mut total.corruptions, &chunk.corruptions) {
+        total.extend_from_slice(chunk);
+    }
+    total.resumes += chunk.resumes;
+    total.paused_iterations += chunk.paused_iterations;
+    total.paused_time += chunk.paused_time;
+}
+
+fn add_counts(total: &mut FrameCounts, chunk: &FrameCounts) {
+    total.event += chunk.event;
+    total.main += chunk.main;
+    total.slow += chunk.slow;
+    total.gps += chunk.gps;
+    total.gps_home += chunk.gps_home;
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec;
+    use alloc::vec::Vec;
+
+    use test_case::case;
+
+    use super::*;
+    use crate::data::EventFrame;
+    use crate::writer::Writer;
+    use crate::{DataParser, File};
+
+    #[derive(Debug, PartialEq)]
+    enum Frame {
+        Event(EventFrame),
+        Main(u32, u64, Vec<u32>),
+        Slow(Vec<u32>),
+        Gps(u64, Vec<u32>),
+    }
+
+    impl From<ParseEvent<'_, '_, '_>> for Frame {
+        fn from(event: ParseEvent) -> Self {
+            match event {
+                ParseEvent::Event(event) => Self::Event(event),
+                ParseEvent::Main(main) => {
+                    Self::Main(main.raw.iteration, main.raw.time, main.raw.values.clone())
+                }
+                ParseEvent::Slow(slow) => Self::Slow(slow.raw.0),
+                ParseEvent::Gps(gps) => Self::Gps(gps.raw.time, gps.raw.values),
+            }
+        }
+    }
+
+    fn counts(stats: &Stats) -> ([usize; 5], [usize; 5], usize) {
+        let counts = |counts: &FrameCounts| {
+            [
+                counts.event,
+                counts.main,
+                counts.slow,
+                counts.gps,
+                counts.gps_home,
+            ]
+        };
+
+        (
+            counts(&stats.counts),
+            counts(&stats.corrupted),
+            stats.skipped_bytes,
+        )
+    }
+
+    /// Has a single GPS home frame at the start, and GPS frames in later chunks
+    fn gps_log(start: u64) -> Vec<u8> {
+        let log = include_bytes!("../tests/logs/gps.bbl");
+        let headers = Headers::parse(&mut Reader::new(log)).unwrap();
+
+        let mut writer = Writer::new(&headers);
+        writer.write_gps_home(100, -100).unwrap();
+
+        for i in 0..20 {
+            let time = start + 1_000 * u64::from(i);
+            writer.write_intra(32 * i, time, &[1]).unwrap();
+            writer.write_slow(&[i, 0, 0, 1, 1]).unwrap();
+            writer.write_inter(32 * i + 2, time + 10, &[2]).unwrap();
+            writer.write_gps(time + 15, &[8, 101, 0, i, 0, 0]).unwrap();
+        }
+
+        writer.into_bytes()
+    }
+
+    #[case(include_bytes!("../tests/logs/error-recovery.bbl"), 100; "error recovery")]
+    #[case(include_bytes!("../tests/logs/error-recovery.bbl"), 1000; "error recovery large chunks")]
+    #[case(&gps_log(0), 40; "gps")]
+    #[case(&gps_log(u64::from(u32::MAX) - 10_000), 40; "time rollover")]
+    fn matches_sequential(log: &[u8], chunk_size: usize) {
+        let file = File::new(log);
+        let mut reader = file.get_reader(0);
+        let headers = Headers::parse(&mut reader).unwrap();
+
+        let mut parser = DataParser::new(reader.clone(), &headers);
+        parser.record_corruptions();
+        let mut expected = Vec::new();
+        while let Some(event) = parser.next() {
+            expected.push(Frame::from(event));
+        }
+
+        let decoded = decode_with_chunk_size(reader, &headers, chunk_size, true);
+        let frames = decoded.events().map(Frame::from).collect::<Vec<_>>();
+
+        assert!(!frames.is_empty());
+        assert_eq!(expected, frames);
+        assert_eq!(counts(parser.stats()), counts(decoded.stats()));
+        assert_eq!(parser.stats().corruptions, decoded.stats().corruptions);
+    }
+
+    #[test]
+    fn misaligned_starts() {
+        let log = gps_log(u64::from(u32::MAX) - 10_000);
+        let file = File::new(&log);
+        let mut reader = file.get_reader(0);
+        let headers = Headers::parse(&mut reader).unwrap();
+
+        let mut parser = DataParser::new(reader.clone(), &headers);
+        let mut expected = Vec::new();
+        let mut starts = vec![reader.position()];
+        while let Some(event) = parser.next() {
+            let intra = matches!(event, ParseEvent::Main(ref main) if main.raw.intra);
+            expected.push(Frame::from(event));
+
+            if intra {
+                // Every other chunk starts in the middle of an intra frame
+                let offset = parser.frame_offset();
+                starts.extend([offset, offset + 1]);
+            }
+        }
+
+        let decoded = decode_chunks(reader, &headers, &starts, false);
+        let frames = decoded.events().map(Frame::from).collect::<Vec<_>>();
+
+        assert_eq!(expected, frames);
+        assert_eq!(counts(parser.stats()), counts(decoded.stats()));
+    }
+}
@@ -0,0 +1,151 @@
+use super::zig_zag_decode;
+use crate::parser::{InternalError, InternalResult};
+use crate::Reader;
+
+/// Reads individual bits, most significant first.
+///
+/// All Elias delta fields in a frame are packed into one bit stream, even if
+/// they are split by fields using another encoding, so a single `BitReader` is
+/// shared by the whole frame. A byte is only taken from the [`Reader`] once its
+/// first bit is needed, and any unread bits are discarded at the end of the
+/// frame.
+#[derive(Debug)]
+pub(crate) struct BitReader {
+    byte: u8,
+    /// Number of unread bits remaining in `byte`
+    remaining: u8,
+}
+
+impl BitReader {
+    pub(crate) const fn new() -> Self {
+        Self {
+            byte: 0,
+            remaining: 0,
+        }
+    }
+
+    fn read_bit(&mut self, data: &mut Reader) -> InternalResult<bool> {
+        if self.remaining == 0 {
+            self.byte = data.read_u8().ok_or(InternalError::Eof)?;
+            self.remaining = 8;
+        }
+
+        self.remaining -= 1;
+        Ok((self.byte >> self.remaining) & 1 == 1)
+    }
+
+    fn read_bits(&mut self, data: &mut Reader, count: u8) -> InternalResult<u32> {
+        debug_assert!(count <= 32);
+
+        let mut bits = 0;
+        for _ in 0..count {
+            bits = (bits << 1) | u32::from(self.read_bit(data)?);
+        }
+
+        Ok(bits)
+    }
+}
+
+pub(crate) fn elias_delta(data: &mut Reader, bits: &mut BitReader) -> InternalResult<u32> {
+    // The length of the value needs at most 6 bits, so at most 5 leading zeros
+    let mut length_bits = 0;
+    while !bits.read_bit(data)? {
+        length_bits += 1;
+
+        if length_bits > 5 {
+            return Err(InternalError::Retry);
+        }
+    }
+
+    let length = ((1 << length_bits) | bits.read_bits(data, length_bits)?) - 1;
+    if length > 31 {
+        return Err(InternalError::Retry);
+    }
+
+    // `length` is at most 31, so this cannot truncate
+    #[allow(clippy::cast_possible_truncation)]
+    let value = (1 << length) | bits.read_bits(data, length as u8)?;
+
+    // Values are offset by 1 to be able to encode 0, which leaves `u32::MAX - 1`
+    // and `u32::MAX` sharing an encoding followed by one extra bit
+    if value == u32::MAX {
+        return Ok(if bits.read_bit(data)? {
+            u32::MAX
+        } else {
+            u32::MAX - 1
+        });
+    }
+
+    Ok(value - 1)
+}
+
+pub(crate) fn elias_delta_signed(data: &mut Reader, bits: &mut BitReader) -> InternalResult<i32> {
+    elias_delta(data, bits).map(zig_zag_decode)
+}
+
+#[cfg(test)]
+mod test {
+    use alloc::vec;
+    use alloc::vec::Vec;
+
+    use test_case::case;
+
+    use super::*;
+
+    fn read(bytes: &[u8], count: usize) -> (Vec<u32>, usize) {
+        let mut data = Reader::new(bytes);
+
+        let mut bits = BitReader::new();
+        let values = (0..count)
+            .map(|_| elias_delta(&mut data, &mut bits).unwrap())
+            .collect();
+
+        (values, data.position())
+    }
+
+    #[case(&[0b1000_0000] => 0; "zero")]
+    #[case(&[0b0100_0000] => 1; "one")]
+    #[case(&[0b0101_0000] => 2; "two")]
+    #[case(&[0b0110_0000] => 3; "three")]
+    #[case(&[0b0010_0000] => 7; "seven")]
+    #[case(&[0b0011_1100, 0b1010_0000] => 100; "one hundred")]
+    #[case(&[0x04, 0x1F, 0xFF, 0xFF, 0xFF, 0x80] => u32::MAX - 2; "almost max")]
+    #[case(&[0x04, 0x1F, 0xFF, 0xFF, 0xFF, 0xC0] => u32::MAX - 1; "max minus one")]
+    #[case(&[0x04, 0x1F, 0xFF, 0xFF, 0xFF, 0xE0] => u32::MAX; "max")]
+    fn unsigned(bytes: &[u8]) -> u32 {
+        let (values, consumed) = read(bytes, 1);
+        assert_eq!(bytes.len(), consumed);
+        values[0]
+    }
+
+    #[test]
+    fn packed() {
+        let (values, consumed) = read(&[0b1010_0010, 0b1100_0000], 4);
+        assert_eq!(vec![0, 1, 2, 0], values);
+        assert_eq!(2, consumed);
+    }
+
+    #[test]
+    fn signed() {
+        let mut data = Reader::new(&[0b0100_1010, 0b1000_0000]);
+        let mut bits = BitReader::new();
+
+        assert_eq!(-1, elias_delta_signed(&mut data, &mut bits).unwrap());
+        assert_eq!(0, elias_delta_signed(&mut data, &mut bits).unwrap());
+        assert_eq!(1, elias_delta_signed(&mut data, &mut bits).unwrap());
+    }
+
+    #[test]
+    #[should_panic(expected = "Retry")]
+    fn too_long() {
+        let mut data = Reader::new(&[0, 0xFF]);
+        elias_delta(&mut data, &mut BitReader::new()).unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "Eof")]
+    fn eof() {
+        let mut data = Reader::new(&[0b0001_0000]);
+        elias_delta(&mut data, &mut BitReader::new()).unwrap();
+    }
+}
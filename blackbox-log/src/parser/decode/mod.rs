@@ -1,19 +1,24 @@
 #![allow(clippy::cast_possible_truncation)]
 
+mod elias_delta;
 mod negative_14_bit;
 mod tagged_16;
 mod tagged_32;
+mod tagged_32_variable;
 mod tagged_variable;
 mod variable;
 
 use alloc::vec::Vec;
 
+pub(crate) use self::elias_delta::{elias_delta, elias_delta_signed, BitReader};
 pub(crate) use self::negative_14_bit::negative_14_bit;
-pub(crate) use self::tagged_16::tagged_16;
+pub(crate) use self::tagged_16::{tagged_16, tagged_16_v1};
 pub(crate) use self::tagged_32::tagged_32;
+pub(crate) use self::tagged_32_variable::tagged_32_variable;
 pub(crate) use self::tagged_variable::tagged_variable;
 pub(crate) use self::variable::{variable, variable_signed};
 use super::InternalResult;
+use crate::headers::LogVersion;
 use crate::utils::{as_i32, as_u32};
 use crate::Reader;
 
@@ -28,6 +33,14 @@ pub(crate) enum Encoding {
         /// Unsigned variable byte, but negated after decoding. Value fits in 14
         /// bits
         Negative14Bit = 3,
+        /// Unsigned [Elias delta code][wiki]. All Elias delta fields in a frame
+        /// are packed into one bit stream, which is only padded to a whole byte
+        /// at the end of the frame.
+        ///
+        /// [wiki]: https://en.wikipedia.org/wiki/Elias_delta_coding
+        EliasDelta = 4,
+        /// Signed (zig-zag encoded) version of [`Encoding::EliasDelta`]
+        EliasDeltaSigned = 5,
         TaggedVariable = 6,
         Tagged32 = 7,
         /// 1 tag byte containing 4 2 bit tags, followed by 4 fields
@@ -38,9 +51,14 @@ pub(crate) enum Encoding {
         /// | 1   | 4                   |
         /// | 2   | 8                   |
         /// | 3   | 16                  |
+        ///
+        /// The layout of the fields differs between [`LogVersion`]s.
         Tagged16 = 8,
         /// Nothing is written to the log, assume value is 0
         Null = 9,
+        /// Like [`Encoding::Tagged32`], but with 5-5-4 and 8-7-7 bit layouts
+        /// instead of 4 and 6 bits for all three fields
+        Tagged32Variable = 10,
     }
 }
 
@@ -49,17 +67,22 @@ pub(crate) const fn is_signed(&self) -> bool {
         match self {
             Self::VariableSigned
             | Self::Negative14Bit
+            | Self::EliasDeltaSigned
             | Self::TaggedVariable
             | Self::Tagged32
-            | Self::Tagged16 => true,
-            Self::Variable | Self::Null => false,
+            | Self::Tagged16
+            | Self::Tagged32Variable => true,
+            Self::Variable | Self::EliasDelta | Self::Null => false,
         }
     }
 
     pub(crate) const fn max_chunk_size(&self) -> usize {
         match self {
+            // Elias delta fields share one bit stream per frame, so chunks never need to
+            // be split
+            Self::EliasDelta | Self::EliasDeltaSigned => usize::MAX,
             Self::TaggedVariable => 8,
-            Self::Tagged32 => 3,
+            Self::Tagged32 | Self::Tagged32Variable => 3,
             Self::Tagged16 => 4,
             Self::VariableSigned | Self::Variable | Self::Negative14Bit | Self::Null => 1,
         }
@@ -68,6 +91,8 @@ pub(crate) const fn max_chunk_size(&self) -> usize {
     pub(crate) fn decode_into(
         &self,
         data: &mut Reader,
+        bits: &mut BitReader,
+        version: LogVersion,
         extra: usize,
         into: &mut Vec<u32>,
     ) -> InternalResult<()> {
@@ -78,12 +103,30 @@ pub(crate) fn decode_into(
 
             Self::Negative14Bit => into.push(as_u32(negative_14_bit(data)?)),
 
+            Self::EliasDelta => {
+                for _ in range {
+                    into.push(elias_delta(data, bits)?);
+                }
+            }
+            Self::EliasDeltaSigned => {
+                for _ in range {
+                    into.push(as_u32(elias_delta_signed(data, bits)?));
+                }
+            }
+
             Self::TaggedVariable => {
                 into.extend_from_slice(&tagged_variable(data, extra)?.map(as_u32)[range]);
             }
             Self::Tagged32 => into.extend_from_slice(&tagged_32(data)?.map(as_u32)[range]),
+            Self::Tagged32Variable => {
+                into.extend_from_slice(&tagged_32_variable(data)?.map(as_u32)[range]);
+            }
             Self::Tagged16 => {
-                into.extend_from_slice(&tagged_16(data)?.map(|x| as_u32(x.into()))[range]);
+                let values = match version {
+                    LogVersion::V1 => tagged_16_v1(data)?,
+                    LogVersion::V2 => tagged_16(data)?,
+                };
+                into.extend_from_slice(&values.map(|x| as_u32(x.into()))[range]);
             }
 
             Self::Null => into.push(0),
@@ -58,6 +58,42 @@
     Ok(result)
 }
 
+/// Decodes the original version of [`tagged_16`] used by
+/// [`LogVersion::V1`](crate::headers::LogVersion::V1) logs.
+///
+/// Here, 4 bit fields are always read in pairs from a single byte, low nibble
+/// first, and 16 bit fields are little endian.
+pub(crate) fn tagged_16_v1(data: &mut Reader) -> InternalResult<[i16; COUNT]> {
+    let mut tags = data.read_u8().ok_or(InternalError::Eof)?;
+
+    let mut result = [0; COUNT];
+    let mut i = 0;
+
+    while i < COUNT {
+        match tags & 3 {
+            0 => result[i] = 0,
+            1 => {
+                let byte = data.read_u8().ok_or(InternalError::Eof)?;
+                result[i] = i4_to_i16(byte & 0xF);
+
+                i += 1;
+                tags >>= 2;
+
+                if let Some(result) = result.get_mut(i) {
+                    *result = i4_to_i16(byte >> 4);
+                }
+            }
+            2 => result[i] = data.read_i8().ok_or(InternalError::Eof)?.into(),
+            3.. => result[i] = data.read_i16().ok_or(InternalError::Eof)?,
+        }
+
+        i += 1;
+        tags >>= 2;
+    }
+
+    Ok(result)
+}
+
 #[inline]
 fn i4_to_i16(nibble: u8) -> i16 {
     sign_extend::<4>(nibble.into()) as i16
@@ -124,4 +160,14 @@ fn tag_order_v2() {
         let mut bits = Reader::new(bytes);
         tagged_16(&mut bits).unwrap()
     }
+
+    #[case(&[0x00] => [0; 4]; "all zeros")]
+    #[case(&[0x55, 0x21, 0x43] => [1, 2, 3, 4]; "nibble pairs low first")]
+    #[case(&[0x05, 0xF7] => [7, -1, 0, 0]; "nibble pair skips tag")]
+    #[case(&[0b1110_0100, 0x21, 0x03, 0x00] => [0, 1, 2, 3]; "tag order v1")]
+    #[case(&[0xC0, 0x3D, 0xB5] => [0, 0, 0, -19139]; "16 bit low byte first")]
+    fn v1(bytes: &[u8]) -> [i16; 4] {
+        let mut bits = Reader::new(bytes);
+        tagged_16_v1(&mut bits).unwrap()
+    }
 }
@@ -0,0 +1,118 @@
+use super::sign_extend;
+use crate::parser::{InternalError, InternalResult};
+use crate::utils::{as_i16, as_i32, as_i8};
+use crate::Reader;
+
+const COUNT: usize = 3;
+
+pub(crate) fn tagged_32_variable(data: &mut Reader) -> InternalResult<[i32; COUNT]> {
+    fn read_u8_or_eof(bytes: &mut Reader) -> InternalResult<u8> {
+        bytes.read_u8().ok_or(InternalError::Eof)
+    }
+
+    let mut result = [0; COUNT];
+
+    let byte = read_u8_or_eof(data)?;
+    match (byte & 0xC0) >> 6 {
+        // 2 bits
+        0 => {
+            #[inline(always)]
+            fn convert(x: u8) -> i32 {
+                sign_extend::<2>((x & 3).into())
+            }
+
+            result[0] = convert(byte >> 4);
+            result[1] = convert(byte >> 2);
+            result[2] = convert(byte);
+        }
+
+        // 5, 5, 4 bits
+        1 => {
+            let next = read_u8_or_eof(data)?;
+
+            result[0] = sign_extend::<5>(((byte >> 1) & 0x1F).into());
+            result[1] = sign_extend::<5>((((byte & 1) << 4) | (next >> 4)).into());
+            result[2] = sign_extend::<4>((next & 0x0F).into());
+        }
+
+        // 8, 7, 7 bits
+        2 => {
+            let second = read_u8_or_eof(data)?;
+            let third = read_u8_or_eof(data)?;
+
+            result[0] = sign_extend::<8>((((byte & 0x3F) << 2) | (second >> 6)).into());
+            result[1] = sign_extend::<7>((((second & 0x3F) << 1) | (third >> 7)).into());
+            result[2] = sign_extend::<7>((third & 0x7F).into());
+        }
+
+        3.. => {
+            let mut tags = byte & 0x3F;
+            for x in &mut result {
+                let tag = tags & 3;
+                tags >>= 2;
+
+                *x = match tag {
+                    // 8 bits
+                    0 => {
+                        let x = read_u8_or_eof(data)?;
+                        as_i8(x).into()
+                    }
+
+                    // 16 bits
+                    1 => {
+                        let value = data.read_u16().ok_or(InternalError::Eof)?;
+                        as_i16(value).into()
+                    }
+
+                    // 24 bits
+                    2 => {
+                        let x = data.read_u24().ok_or(InternalError::Eof)?;
+                        sign_extend::<24>(x)
+                    }
+
+                    // 32 bits
+                    3.. => {
+                        let value = data.read_u32().ok_or(InternalError::Eof)?;
+                        as_i32(value)
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(result)
+}
+
+#[cfg(test)]
+mod test {
+    use test_case::case;
+
+    use super::*;
+
+    #[case(&[0x0D] => [0, -1, 1]; "2 bits")]
+    #[case(&[0b0100_0010, 0b0010_0011] => [1, 2, 3]; "5 5 4 bits")]
+    #[case(&[0b0111_1111, 0b1111_1000] => [-1, -1, -8]; "5 5 4 bits negative")]
+    #[case(&[0b1000_0000, 0b0100_0000, 0b1000_0011] => [1, 1, 3]; "8 7 7 bits")]
+    #[case(&[0b1010_0000, 0b0001_1111, 0b1100_0000] => [-128, 63, -64]; "8 7 7 bits extremes")]
+    #[case(&[0xE4, 0x01, 0x02, 0x00, 0x03, 0x00, 0x00] => [1, 2, 3]; "8 16 24 bits")]
+    fn decode(bytes: &[u8]) -> [i32; 3] {
+        let mut b = Reader::new(bytes);
+        let result = tagged_32_variable(&mut b).unwrap();
+        assert!(b.is_empty());
+        result
+    }
+
+    #[test]
+    #[should_panic(expected = "Eof")]
+    fn eof_554_bit() {
+        let mut b = Reader::new(&[0x40]);
+        tagged_32_variable(&mut b).unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "Eof")]
+    fn eof_877_bit() {
+        let mut b = Reader::new(&[0x80, 0x00]);
+        tagged_32_variable(&mut b).unwrap();
+    }
+}
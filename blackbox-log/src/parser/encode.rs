@@ -0,0 +1,441 @@
+//! Inverses of the decoders in [`decode`](super::decode).
+
+#![allow(clippy::cast_possible_truncation)]
+
+use alloc::vec::Vec;
+
+use super::Encoding;
+use crate::headers::LogVersion;
+use crate::utils::{as_i32, as_u16, as_u32};
+
+impl Encoding {
+    /// Encodes one chunk of consecutive fields sharing this encoding, as
+    /// grouped by [`Encoding::max_chunk_size`].
+    ///
+    /// On failure, returns the index within `values` of the first value that
+    /// cannot be represented.
+    pub(crate) fn encode_into(
+        &self,
+        out: &mut Vec<u8>,
+        bits: &mut BitWriter,
+        version: LogVersion,
+        values: &[u32],
+    ) -> Result<(), usize> {
+        debug_assert!(!values.is_empty() && values.len() <= self.max_chunk_size());
+
+        match self {
+            Self::VariableSigned => variable_signed(out, as_i32(values[0])),
+            Self::Variable => variable(out, values[0]),
+
+            Self::Negative14Bit => negative_14_bit(out, as_i32(values[0])).map_err(|()| 0_usize)?,
+
+            Self::EliasDelta => {
+                for &value in values {
+                    elias_delta(out, bits, value);
+                }
+            }
+            Self::EliasDeltaSigned => {
+                for &value in values {
+                    elias_delta_signed(out, bits, as_i32(value));
+                }
+            }
+
+            Self::TaggedVariable => {
+                tagged_variable(out, &values.iter().map(|&x| as_i32(x)).collect::<Vec<_>>())
+            }
+            Self::Tagged32 => tagged_32(out, pad(values)),
+            Self::Tagged16 => {
+                let mut padded = [0; 4];
+                for (i, (&value, padded)) in values.iter().zip(&mut padded).enumerate() {
+                    *padded = i16::try_from(as_i32(value)).map_err(|_| i)?;
+                }
+
+                match version {
+                    LogVersion::V1 => tagged_16_v1(out, padded),
+                    LogVersion::V2 => tagged_16(out, padded),
+                }
+            }
+
+            Self::Null => {
+                if let Some(i) = values.iter().position(|&x| x != 0) {
+                    return Err(i);
+                }
+            }
+
+            Self::Tagged32Variable => tagged_32_variable(out, pad(values)),
+        }
+
+        Ok(())
+    }
+}
+
+/// Pads a partial chunk of 3 values with zeros
+fn pad(values: &[u32]) -> [i32; 3] {
+    let mut padded = [0; 3];
+    for (padded, &value) in padded.iter_mut().zip(values) {
+        *padded = as_i32(value);
+    }
+    padded
+}
+
+/// Returns the lowest `bits` bits of `x`
+#[inline]
+const fn mask(x: i32, bits: u32) -> u8 {
+    (as_u32(x) & ((1 << bits) - 1)) as u8
+}
+
+/// Returns `true` if `x` fits in a `BITS` bit two's complement integer
+#[inline]
+const fn fits<const BITS: u32>(x: i32) -> bool {
+    let min = -(1 << (BITS - 1));
+    let max = (1 << (BITS - 1)) - 1;
+    min <= x && x <= max
+}
+
+#[inline]
+const fn zig_zag_encode(value: i32) -> u32 {
+    as_u32((value << 1) ^ (value >> 31))
+}
+
+pub(crate) fn variable(out: &mut Vec<u8>, mut value: u32) {
+    while value >= 0x80 {
+        out.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+
+    out.push(value as u8);
+}
+
+pub(crate) fn variable_signed(out: &mut Vec<u8>, value: i32) {
+    variable(out, zig_zag_encode(value));
+}
+
+fn negative_14_bit(out: &mut Vec<u8>, value: i32) -> Result<(), ()> {
+    if !(-0x1FFF..=0x2000).contains(&value) {
+        return Err(());
+    }
+
+    variable(out, as_u32(-value) & 0x3FFF);
+    Ok(())
+}
+
+/// Writes individual bits, most significant first.
+///
+/// Inverse of [`BitReader`](super::decode::BitReader): one `BitWriter` is
+/// shared by all Elias delta fields in a frame. Each byte is reserved in the
+/// output as soon as its first bit is written, so fields using other encodings
+/// are written after it, and the last byte is implicitly padded with zeros.
+#[derive(Debug)]
+pub(crate) struct BitWriter {
+    /// Index of the current byte in the output
+    index: usize,
+    /// Number of bits already written to the current byte
+    len: u8,
+}
+
+impl BitWriter {
+    pub(crate) const fn new() -> Self {
+        Self { index: 0, len: 8 }
+    }
+
+    fn write_bits(&mut self, out: &mut Vec<u8>, bits: u32, count: u8) {
+        debug_assert!(count <= 32);
+
+        for i in (0..count).rev() {
+            if self.len == 8 {
+                self.index = out.len();
+                self.len = 0;
+                out.push(0);
+            }
+
+            out[self.index] |= (((bits >> i) & 1) as u8) << (7 - self.len);
+            self.len += 1;
+        }
+    }
+}
+
+fn elias_delta(out: &mut Vec<u8>, bits: &mut BitWriter, value: u32) {
+    // Offset by 1 to be able to encode 0. `u32::MAX - 1` & `u32::MAX` share the
+    // code for `u32::MAX` with one extra bit to tell them apart
+    let offset = value.saturating_add(1);
+
+    let length = 31 - offset.leading_zeros() as u8;
+    let length_bits = 7 - (length + 1).leading_zeros() as u8;
+
+    bits.write_bits(out, 0, length_bits);
+    bits.write_bits(out, u32::from(length) + 1, length_bits + 1);
+    bits.write_bits(out, offset, length);
+
+    if offset == u32::MAX {
+        bits.write_bits(out, u32::from(value == u32::MAX), 1);
+    }
+}
+
+fn elias_delta_signed(out: &mut Vec<u8>, bits: &mut BitWriter, value: i32) {
+    elias_delta(out, bits, zig_zag_encode(value));
+}
+
+fn tagged_variable(out: &mut Vec<u8>, values: &[i32]) {
+    debug_assert!(values.len() <= 8);
+
+    if let [value] = values {
+        variable_signed(out, *value);
+        return;
+    }
+
+    let header = values
+        .iter()
+        .enumerate()
+        .filter(|(_, &x)| x != 0)
+        .fold(0, |header, (i, _)| header | (1 << i));
+    out.push(header);
+
+    for &value in values.iter().filter(|&&x| x != 0) {
+        variable_signed(out, value);
+    }
+}
+
+fn tagged_32(out: &mut Vec<u8>, values: [i32; 3]) {
+    if values.iter().all(|&x| fits::<2>(x)) {
+        out.push(mask(values[0], 2) << 4 | mask(values[1], 2) << 2 | mask(values[2], 2));
+    } else if values.iter().all(|&x| fits::<4>(x)) {
+        out.push(0x40 | mask(values[0], 4));
+        out.push(mask(values[1], 4) << 4 | mask(values[2], 4));
+    } else if values.iter().all(|&x| fits::<6>(x)) {
+        out.push(0x80 | mask(values[0], 6));
+        out.push(mask(values[1], 6));
+        out.push(mask(values[2], 6));
+    } else {
+        tagged_32_bytes(out, values);
+    }
+}
+
+fn tagged_32_variable(out: &mut Vec<u8>, values: [i32; 3]) {
+    let [a, b, c] = values;
+
+    if values.iter().all(|&x| fits::<2>(x)) {
+        out.push(mask(a, 2) << 4 | mask(b, 2) << 2 | mask(c, 2));
+    } else if fits::<5>(a) && fits::<5>(b) && fits::<4>(c) {
+        let (a, b) = (mask(a, 5), mask(b, 5));
+        out.push(0x40 | a << 1 | b >> 4);
+        out.push((b & 0x0F) << 4 | mask(c, 4));
+    } else if fits::<8>(a) && fits::<7>(b) && fits::<7>(c) {
+        let (a, b) = (mask(a, 8), mask(b, 7));
+        out.push(0x80 | a >> 2);
+        out.push((a & 3) << 6 | b >> 1);
+        out.push((b & 1) << 7 | mask(c, 7));
+    } else {
+        tagged_32_bytes(out, values);
+    }
+}
+
+/// Shared layout for [`Encoding::Tagged32`] & [`Encoding::Tagged32Variable`]
+/// with each value stored in 1-4 whole bytes
+fn tagged_32_bytes(out: &mut Vec<u8>, values: [i32; 3]) {
+    let width = |x: i32| -> u8 {
+        if fits::<8>(x) {
+            0
+        } else if fits::<16>(x) {
+            1
+        } else if fits::<24>(x) {
+            2
+        } else {
+            3
+        }
+    };
+
+    let tags = values
+        .iter()
+        .rev()
+        .fold(0, |tags, &x| (tags << 2) | width(x));
+    out.push(0xC0 | tags);
+
+    for value in values {
+        let len = usize::from(width(value)) + 1;
+        out.extend_from_slice(&value.to_le_bytes()[..len]);
+    }
+}
+
+/// Nibbles are packed high first, and 16 bit values are big endian
+fn tagged_16(out: &mut Vec<u8>, values: [i16; 4]) {
+    let tag = |x: i16| -> u8 {
+        if x == 0 {
+            0
+        } else if fits::<4>(x.into()) {
+            1
+        } else if fits::<8>(x.into()) {
+            2
+        } else {
+            3
+        }
+    };
+
+    let tags = values.iter().rev().fold(0, |tags, &x| (tags << 2) | tag(x));
+    out.push(tags);
+
+    let mut nibbles = Vec::with_capacity(16);
+    for value in values {
+        let count = match tag(value) {
+            0 => 0,
+            1 => 1,
+            2 => 2,
+            _ => 4,
+        };
+
+        let value = as_u16(value);
+        for i in (0..count).rev() {
+            nibbles.push(((value >> (i * 4)) & 0xF) as u8);
+        }
+    }
+
+    for pair in nibbles.chunks(2) {
+        out.push(pair[0] << 4 | pair.get(1).copied().unwrap_or(0));
+    }
+}
+
+/// 4 bit values are packed in pairs, low first, and 16 bit values are little
+/// endian
+fn tagged_16_v1(out: &mut Vec<u8>, values: [i16; 4]) {
+    let tag_index = out.len();
+    out.push(0);
+
+    let mut tags = 0;
+    let mut i = 0;
+    while i < values.len() {
+        let value = values[i];
+        let next = values.get(i + 1).copied().unwrap_or(0);
+
+        let tag = if value == 0 {
+            0
+        } else if fits::<4>(value.into()) && fits::<4>(next.into()) {
+            out.push((as_u16(value) as u8 & 0xF) | (as_u16(next) as u8) << 4);
+            i += 1;
+            1
+        } else if fits::<8>(value.into()) {
+            out.push(as_u16(value) as u8);
+            2
+        } else {
+            out.extend_from_slice(&value.to_le_bytes());
+            3
+        };
+
+        // Pairs of 4 bit values leave the tag of the second value empty
+        tags |= tag << (2 * (i - usize::from(tag == 1)));
+        i += 1;
+    }
+
+    out[tag_index] = tags;
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+    use alloc::{format, vec};
+
+    use proptest::prelude::*;
+
+    use super::*;
+    use crate::parser::decode::BitReader;
+    use crate::Reader;
+
+    fn round_trip(
+        encoding: Encoding,
+        version: LogVersion,
+        values: &[u32],
+    ) -> Result<Vec<u32>, TestCaseError> {
+        let mut bytes = Vec::new();
+        encoding
+            .encode_into(&mut bytes, &mut BitWriter::new(), version, values)
+            .map_err(|i| TestCaseError::fail(format!("failed to encode value {i}")))?;
+
+        let mut data = Reader::new(&bytes);
+        let mut decoded = Vec::new();
+        encoding
+            .decode_into(
+                &mut data,
+                &mut BitReader::new(),
+                version,
+                values.len() - 1,
+                &mut decoded,
+            )
+            .map_err(|err| TestCaseError::fail(format!("{err:?}")))?;
+
+        prop_assert!(data.is_empty(), "not all bytes were decoded");
+        Ok(decoded)
+    }
+
+    fn chunk(
+        encoding: Encoding,
+        values: impl Strategy<Value = i32>,
+    ) -> impl Strategy<Value = Vec<u32>> {
+        prop::collection::vec(
+            values.prop_map(as_u32),
+            1..=encoding.max_chunk_size().min(8),
+        )
+    }
+
+    proptest! {
+        #[test]
+        fn variable(value: u32) {
+            prop_assert_eq!(vec![value], round_trip(Encoding::Variable, LogVersion::V2, &[value])?);
+        }
+
+        #[test]
+        fn variable_signed(value: i32) {
+            let value = as_u32(value);
+            prop_assert_eq!(vec![value], round_trip(Encoding::VariableSigned, LogVersion::V2, &[value])?);
+        }
+
+        #[test]
+        fn negative_14_bit(value in -0x1FFF..=0x2000) {
+            let value = as_u32(value);
+            prop_assert_eq!(vec![value], round_trip(Encoding::Negative14Bit, LogVersion::V2, &[value])?);
+        }
+
+        #[test]
+        fn elias_delta(values in chunk(Encoding::EliasDelta, any::<i32>())) {
+            prop_assert_eq!(&values, &round_trip(Encoding::EliasDelta, LogVersion::V2, &values)?);
+        }
+
+        #[test]
+        fn elias_delta_signed(values in chunk(Encoding::EliasDeltaSigned, any::<i32>())) {
+            prop_assert_eq!(&values, &round_trip(Encoding::EliasDeltaSigned, LogVersion::V2, &values)?);
+        }
+
+        #[test]
+        fn tagged_variable(values in chunk(Encoding::TaggedVariable, prop_oneof![Just(0), any::<i32>()])) {
+            prop_assert_eq!(&values, &round_trip(Encoding::TaggedVariable, LogVersion::V2, &values)?);
+        }
+
+        #[test]
+        fn tagged_32(values in chunk(Encoding::Tagged32, prop_oneof![-32..32, any::<i32>()])) {
+            prop_assert_eq!(&values, &round_trip(Encoding::Tagged32, LogVersion::V2, &values)?);
+        }
+
+        #[test]
+        fn tagged_32_variable(values in chunk(Encoding::Tagged32Variable, prop_oneof![-128..128, any::<i32>()])) {
+            prop_assert_eq!(&values, &round_trip(Encoding::Tagged32Variable, LogVersion::V2, &values)?);
+        }
+
+        #[test]
+        fn tagged_16(
+            values in chunk(Encoding::Tagged16, prop_oneof![-8..8, any::<i16>().prop_map(i32::from)]),
+            version in prop_oneof![Just(LogVersion::V1), Just(LogVersion::V2)],
+        ) {
+            prop_assert_eq!(&values, &round_trip(Encoding::Tagged16, version, &values)?);
+        }
+    }
+
+    #[test]
+    fn unencodable() {
+        let mut out = Vec::new();
+        let encode = |encoding: Encoding, values: &[i32], out: &mut Vec<u8>| {
+            let values = values.iter().copied().map(as_u32).collect::<Vec<_>>();
+            encoding.encode_into(out, &mut BitWriter::new(), LogVersion::V2, &values)
+        };
+
+        assert_eq!(Err(0), encode(Encoding::Negative14Bit, &[0x2001], &mut out));
+        assert_eq!(Err(1), encode(Encoding::Tagged16, &[0, 0x8000], &mut out));
+        assert_eq!(Err(0), encode(Encoding::Null, &[1], &mut out));
+    }
+}
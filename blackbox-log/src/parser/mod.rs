@@ -1,6 +1,8 @@
 pub(crate) mod decode;
+pub(crate) mod encode;
 
 pub(crate) use self::decode::Encoding;
+use crate::utils::as_i32;
 
 pub(crate) type InternalResult<T> = Result<T, InternalError>;
 
@@ -16,3 +18,39 @@ pub(crate) enum InternalError {
 pub(crate) fn to_base_field(field: &str) -> &str {
     field.split_once('[').map_or(field, |(base, _)| base)
 }
+
+/// Expands a timestamp logged as 32 bits into the 64-bit timeline of `last`,
+/// assuming the two are less than half of the 32-bit range apart.
+pub(crate) fn expand_time(time: u32, last: Option<u64>) -> u64 {
+    let Some(last) = last else {
+        return time.into();
+    };
+
+    let delta = as_i32(time.wrapping_sub(truncate_time(last)));
+    last.saturating_add_signed(delta.into())
+}
+
+/// Returns the low 32 bits of `time`, as written by the firmware.
+pub(crate) const fn truncate_time(time: u64) -> u32 {
+    (time & 0xFFFF_FFFF) as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use test_case::case;
+
+    use super::*;
+
+    const WRAP: u64 = 1 << 32;
+
+    #[case(10, None, 10; "first")]
+    #[case(10, Some(5), 10; "forward")]
+    #[case(5, Some(10), 5; "backward")]
+    #[case(10, Some(WRAP - 5), WRAP + 10; "across rollover")]
+    #[case(0xFFFF_FFFB, Some(WRAP + 10), WRAP - 5; "backward across rollover")]
+    #[case(10, Some(3 * WRAP + 5), 3 * WRAP + 10; "after several rollovers")]
+    fn expand_time(time: u32, last: Option<u64>, expected: u64) {
+        assert_eq!(expected, super::expand_time(time, last));
+        assert_eq!(time, truncate_time(expected));
+    }
+}
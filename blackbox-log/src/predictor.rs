@@ -1,6 +1,7 @@
 use core::ops::{Add, Div, Sub};
 
 use super::frame::GpsPosition;
+use crate::parser::truncate_time;
 use crate::utils::{as_i32, as_u32};
 use crate::Headers;
 
@@ -51,7 +52,33 @@ pub(crate) fn apply(
         };
         let _span = _span.enter();
 
-        let diff = match self {
+        let diff = self.predict(signed, current, ctx);
+
+        if signed {
+            let signed = as_i32(value).wrapping_add(as_i32(diff));
+            tracing::trace!(return = signed);
+            as_u32(signed)
+        } else {
+            let x = value.wrapping_add(diff);
+            tracing::trace!(return = x);
+            x
+        }
+    }
+
+    /// Inverse of [`Predictor::apply`], returning the value to be encoded.
+    pub(crate) fn unapply(
+        self,
+        value: u32,
+        signed: bool,
+        current: Option<&[u32]>,
+        ctx: &PredictorContext,
+    ) -> u32 {
+        // Wrapping addition is identical for signed & unsigned values
+        value.wrapping_sub(self.predict(signed, current, ctx))
+    }
+
+    fn predict(self, signed: bool, current: Option<&[u32]>, ctx: &PredictorContext) -> u32 {
+        match self {
             Self::Zero => 0,
             Self::Previous => ctx.last.unwrap_or(0),
             Self::StraightLine => {
@@ -110,21 +137,14 @@ pub(crate) fn apply(
             ),
             Self::FifteenHundred => 1500,
             Self::VBatReference => ctx.headers.vbat_reference.unwrap().into(),
-            Self::LastMainFrameTime => {
-                tracing::debug!("found unhandled {self:?}");
-                0
-            }
+            Self::LastMainFrameTime => ctx.last_main_frame_time.map_or_else(
+                || {
+                    tracing::debug!("found {self:?} without a previous main frame");
+                    0
+                },
+                truncate_time,
+            ),
             Self::MinMotor => ctx.headers.motor_output_range.unwrap().min.into(),
-        };
-
-        if signed {
-            let signed = as_i32(value).wrapping_add(as_i32(diff));
-            tracing::trace!(return = signed);
-            as_u32(signed)
-        } else {
-            let x = value.wrapping_add(diff);
-            tracing::trace!(return = x);
-            x
         }
     }
 
@@ -154,6 +174,7 @@ pub(crate) struct PredictorContext<'a, 'data> {
     last_last: Option<u32>,
     skipped_frames: u32,
     gps_home: Option<GpsPosition>,
+    last_main_frame_time: Option<u64>,
 }
 
 impl<'a, 'data> PredictorContext<'a, 'data> {
@@ -164,6 +185,7 @@ pub(crate) const fn new(headers: &'a Headers<'data>) -> Self {
             last_last: None,
             skipped_frames: 0,
             gps_home: None,
+            last_main_frame_time: None,
         }
     }
 
@@ -174,12 +196,14 @@ pub(crate) const fn with_skipped(headers: &'a Headers<'data>, skipped_frames: u3
             last_last: None,
             skipped_frames,
             gps_home: None,
+            last_main_frame_time: None,
         }
     }
 
-    pub(crate) const fn with_home(
+    pub(crate) const fn with_gps(
         headers: &'a Headers<'data>,
         gps_home: Option<GpsPosition>,
+        last_main_frame_time: Option<u64>,
     ) -> Self {
         Self {
             headers,
@@ -187,6 +211,7 @@ pub(crate) const fn with_home(
             last_last: None,
             skipped_frames: 0,
             gps_home,
+            last_main_frame_time,
         }
     }
 
@@ -41,6 +41,21 @@ pub(crate) fn restore(&mut self, restore: RestorePoint) {
         self.index = restore.0;
     }
 
+    /// Returns all bytes read since `start`.
+    pub(crate) fn bytes_since(&self, start: &RestorePoint) -> &'data [u8] {
+        &self.data[start.0..self.index]
+    }
+
+    /// Moves to `position`, clamped to the end of the data.
+    pub(crate) fn seek(&mut self, position: usize) {
+        self.index = position.min(self.data.len());
+    }
+
+    /// Returns the number of bytes that have been read.
+    pub(crate) const fn position(&self) -> usize {
+        self.index
+    }
+
     /// Advances past all bytes not matching any of the needles, returning
     /// `true` if any are found before the end of the buffer.
     pub(crate) fn skip_until_any(&mut self, needles: &[u8]) -> bool {
@@ -63,7 +78,7 @@ pub(crate) fn skip_until_any(&mut self, needles: &[u8]) -> bool {
 
     /// Returns the number of bytes that have not yet been read.
     #[must_use]
-    const fn remaining(&self) -> usize {
+    pub(crate) const fn remaining(&self) -> usize {
         self.data.len() - self.index
     }
 
@@ -0,0 +1,517 @@
+//! Parsing of logs from any [`io::Read`] in bounded memory.
+//!
+//! Unlike [`File`](crate::File) and [`Reader`], this does not require the
+//! entire file to be loaded up front. Only the header section of each log is
+//! kept in memory as a whole, while the data section is read in chunks as it
+//! is parsed.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use std::fs::File;
+//!
+//! use blackbox_log::prelude::*;
+//! use blackbox_log::stream::{StreamDataParser, StreamReader};
+//! use blackbox_log::Reader;
+//!
+//! let mut stream = StreamReader::new(File::open("LOG00001.BFL")?);
+//!
+//! while let Some(header_bytes) = stream.read_headers()? {
+//!     let Ok(headers) = Headers::parse(&mut Reader::new(&header_bytes)) else {
+//!         continue;
+//!     };
+//!
+//!     let mut parser = StreamDataParser::new(&mut stream, &headers);
+//!     while let Some(event) = parser.next()? {
+//!         if let ParseEvent::Main(main) = event {
+//!             for (value, name) in main.iter().zip(headers.main_frame_def.iter_names()) {
+//!                 println!("{name}: {value:?}");
+//!             }
+//!         }
+//!     }
+//! }
+//! # Ok::<(), std::io::Error>(())
+//! ```
+
+use alloc::vec::Vec;
+use core::fmt;
+use std::io::{self, Read};
+
+use memchr::memmem;
+
+use crate::data::{
+    is_frame_kind, skip_to_frame, CorruptionReason, InternalFrame, ParseEvent, ParserState, Stats,
+};
+use crate::frame::FrameKind;
+use crate::parser::InternalError;
+use crate::{Headers, Reader};
+
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// A buffered wrapper around an [`io::Read`] that yields the logs it contains
+/// one at a time.
+pub struct StreamReader<R> {
+    inner: R,
+    buffer: Vec<u8>,
+    /// Index of the first unconsumed byte in `buffer`
+    start: usize,
+    /// Number of bytes consumed since the start of the current log
+    offset: usize,
+    /// Index in `buffer` of the end of the current log's known data
+    end: usize,
+    /// Set once the start marker of the next log has been found in `buffer`
+    next_log: bool,
+    eof: bool,
+}
+
+impl<R: Read> StreamReader<R> {
+    /// Creates a new `StreamReader` without reading any data.
+    pub fn new(inner: R) -> Self {
+        Self {
+            inner,
+            buffer: Vec::new(),
+            start: 0,
+            offset: 0,
+            end: 0,
+            next_log: false,
+            eof: false,
+        }
+    }
+
+    /// Skips to the start of the next log and reads its complete header
+    /// section, returning `None` if there are no more logs.
+    ///
+    /// The returned buffer can be parsed using [`Headers::parse`]. Afterwards,
+    /// the stream is positioned at the start of the data section, ready to be
+    /// passed to [`StreamDataParser::new`].
+    pub fn read_headers(&mut self) -> io::Result<Option<Vec<u8>>> {
+        if !self.skip_to_marker()? {
+            return Ok(None);
+        }
+        self.offset = 0;
+
+        let mut headers = Vec::new();
+        loop {
+            if self.buffered().is_empty() && !self.fill()? {
+                break;
+            }
+
+            if self.buffered()[0] != b'H' {
+                break;
+            }
+
+            if let Some(len) = memchr::memchr(b'\n', self.buffered()) {
+                headers.extend_from_slice(&self.buffered()[..=len]);
+                self.consume(len + 1);
+            } else if !self.fill()? {
+                headers.extend_from_slice(self.buffered());
+                self.consume(self.buffered().len());
+                break;
+            }
+        }
+
+        Ok(Some(headers))
+    }
+
+    /// Returns the underlying reader. Any buffered data is lost.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    /// Advances to the next log start marker, returning `false` if none is
+    /// found before the end of the stream.
+    fn skip_to_marker(&mut self) -> io::Result<bool> {
+        let finder = memmem::Finder::new(crate::MARKER);
+
+        loop {
+            if let Some(position) = finder.find(self.buffered()) {
+                self.consume(position);
+                return Ok(true);
+            }
+
+            // Keep enough bytes to find a marker split across two reads
+            let keep = crate::MARKER.len() - 1;
+            self.consume(self.buffered().len().saturating_sub(keep));
+
+            if !self.fill()? {
+                return Ok(false);
+            }
+        }
+    }
+
+    fn buffered(&self) -> &[u8] {
+        &self.buffer[self.start..]
+    }
+
+    /// Returns the buffered bytes that belong to the current log, stopping
+    /// before the start marker of the next log.
+    fn log_data(&self) -> &[u8] {
+        &self.buffer[self.start..self.end]
+    }
+
+    /// Returns `true` if no more bytes will be added to
+    /// [`log_data`](Self::log_data) for the current log.
+    fn log_ended(&self) -> bool {
+        self.next_log || self.eof
+    }
+
+    /// Reads more data for the current log, returning `false` if its end had
+    /// already been reached.
+    fn fill_log(&mut self) -> io::Result<bool> {
+        if self.log_ended() {
+            return Ok(false);
+        }
+
+        self.fill()?;
+        self.find_log_end();
+        Ok(true)
+    }
+
+    /// Updates `end` to exclude the next log's start marker and anything after
+    /// it from the current log.
+    fn find_log_end(&mut self) {
+        if let Some(position) = memmem::find(self.buffered(), crate::MARKER) {
+            self.end = self.start + position;
+            self.next_log = true;
+        } else {
+            // Hold back enough bytes that a marker split across two reads is
+            // not parsed as part of this log
+            let keep = if self.eof { 0 } else { crate::MARKER.len() - 1 };
+            self.end = self.buffer.len().saturating_sub(keep).max(self.start);
+            self.next_log = false;
+        }
+    }
+
+    fn consume(&mut self, len: usize) {
+        debug_assert!(len <= self.buffered().len());
+        self.start += len;
+        self.offset += len;
+    }
+
+    /// Reads up to one more chunk into the buffer, discarding any consumed
+    /// bytes first. Returns `false` if the end of the stream has been reached.
+    fn fill(&mut self) -> io::Result<bool> {
+        if self.eof {
+            return Ok(false);
+        }
+
+        self.buffer.drain(..self.start);
+        self.start = 0;
+
+        let len = self.buffer.len();
+        self.buffer.resize(len + CHUNK_SIZE, 0);
+
+        let read = loop {
+            match self.inner.read(&mut self.buffer[len..]) {
+                Ok(read) => break read,
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+                Err(err) => {
+                    self.buffer.truncate(len);
+                    return Err(err);
+                }
+            }
+        };
+
+        self.buffer.truncate(len + read);
+        self.eof = read == 0;
+        Ok(!self.eof)
+    }
+}
+
+impl<R> fmt::Debug for StreamReader<R> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("StreamReader")
+            .field("buffered", &(self.buffer.len() - self.start))
+            .field("eof", &self.eof)
+            .finish_non_exhaustive()
+    }
+}
+
+/// A [`DataParser`](crate::DataParser) that reads the data section from a
+/// [`StreamReader`] as needed.
+#[derive(Debug)]
+pub struct StreamDataParser<'data, 'headers, 'stream, R> {
+    headers: &'headers Headers<'data>,
+    stream: &'stream mut StreamReader<R>,
+    state: ParserState,
+    /// Set while searching for the start of the next frame after corrupted
+    /// data, which may continue past the end of the buffer
+    resync: bool,
+}
+
+impl<'data, 'headers, 'stream, R: Read> StreamDataParser<'data, 'headers, 'stream, R> {
+    /// Constructs a new parser without beginning parsing.
+    ///
+    /// Parsing stops at the end of the stream or the start of the next log,
+    /// whichever comes first.
+    ///
+    /// **Note:** This assumes `stream` is positioned at the start of the data
+    /// section, eg by [`StreamReader::read_headers`].
+    pub fn new(stream: &'stream mut StreamReader<R>, headers: &'headers Headers<'data>) -> Self {
+        stream.find_log_end();
+        Self {
+            headers,
+            stream,
+            state: ParserState::default(),
+            resync: false,
+        }
+    }
+
+    /// Returns the current stats.
+    pub fn stats(&self) -> &Stats {
+        &self.state.stats
+    }
+
+    /// Starts recording the location of each corruption found into
+    /// [`Stats::corruptions`].
+    pub fn record_corruptions(&mut self) {
+        self.state.record_corruptions();
+    }
+
+    /// Returns `true` if the parser has reached the end of the log.
+    pub fn is_done(&self) -> bool {
+        self.state.done
+    }
+
+    /// Continues parsing until the next [`ParseEvent`] can be returned. Returns
+    /// `None` if the parser finds the end of the log.
+    ///
+    /// Any error returned comes from reading the underlying stream.
+    pub fn next<'parser>(
+        &'parser mut self,
+    ) -> io::Result<Option<ParseEvent<'data, 'headers, 'parser>>> {
+        if self.state.done {
+            return Ok(None);
+        }
+
+        loop {
+            if self.resync {
+                let mut data = Reader::new(self.stream.log_data());
+
+                if skip_to_frame(&mut data) {
+                    self.resync = false;
+                    let consumed = data.position();
+                    self.stream.consume(consumed);
+                    self.state.stats.skipped_bytes += consumed;
+                } else {
+                    let consumed = self.stream.log_data().len();
+                    self.stream.consume(consumed);
+                    self.state.stats.skipped_bytes += consumed;
+
+                    if !self.stream.fill_log()? {
+                        return Ok(None);
+                    }
+
+                    continue;
+                }
+            }
+
+            let mut data = Reader::new(self.stream.log_data());
+
+            let Some(byte) = data.read_u8() else {
+                if self.stream.fill_log()? {
+                    continue;
+                }
+
+                return Ok(None);
+            };
+            let restore = data.get_restore_point();
+
+            let offset = self.stream.offset;
+            let Some(kind) = FrameKind::from_byte(byte) else {
+                self.state
+                    .corrupted(offset, None, CorruptionReason::InvalidFrameKind);
+                self.stream.consume(1);
+                self.state.stats.skipped_bytes += 1;
+                self.resync = true;
+                continue;
+            };
+
+            let result = self
+                .state
+                .parse_frame(&mut data, self.headers, offset, kind);
+
+            // The frame may have been cut off at the end of the buffer, or its
+            // validity cannot yet be checked using the next byte, so retry the
+            // whole frame once more data is available
+            let incomplete = match result {
+                Ok(_) => data.peek().is_none(),
+                Err(InternalError::Eof) => true,
+                Err(InternalError::Retry) => false,
+            };
+            if incomplete && !self.stream.log_ended() {
+                self.stream.fill_log()?;
+                continue;
+            }
+
+            match result {
+                // Check for a good frame kind byte, or EOF
+                Ok(frame) if data.peek().map_or(true, is_frame_kind) => {
+                    let consumed = data.position();
+                    self.stream.consume(consumed);
+
+                    if let InternalFrame::GpsHome(gps_home) = frame {
+                        self.state.update_gps_home(gps_home);
+                        continue;
+                    }
+
+                    return Ok(Some(self.state.emit(self.headers, frame)));
+                }
+                Ok(_) | Err(InternalError::Retry) => {
+                    tracing::debug!("found corrupted {kind:?} frame");
+                    let reason = if result.is_ok() {
+                        CorruptionReason::BadTerminator
+                    } else {
+                        CorruptionReason::InvalidFrame
+                    };
+                    self.state.corrupted(offset, Some(kind), reason);
+                    data.restore(restore);
+
+                    let consumed = data.position();
+                    self.stream.consume(consumed);
+                    self.state.stats.skipped_bytes += consumed;
+                    self.resync = true;
+                }
+                Err(InternalError::Eof) => {
+                    tracing::debug!("found unexpected end of file in data section");
+                    self.state
+                        .corrupted(offset, Some(kind), CorruptionReason::UnexpectedEof);
+                    let consumed = self.stream.log_data().len();
+                    self.stream.consume(consumed);
+                    self.state.stats.skipped_bytes += consumed;
+                    return Ok(None);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+
+    use super::*;
+    use crate::frame::{Frame as _, MainValue};
+    use crate::DataParser;
+
+    static DATA: &[u8] = include_bytes!("../tests/logs/error-recovery.bbl");
+
+    /// Only returns a few bytes per read to force frames to be split
+    struct Trickle<'a>(&'a [u8]);
+
+    impl Read for Trickle<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let len = buf.len().min(self.0.len()).min(7);
+            buf[..len].copy_from_slice(&self.0[..len]);
+            self.0 = &self.0[len..];
+            Ok(len)
+        }
+    }
+
+    #[test]
+    fn matches_slice_parser() {
+        let mut reader = Reader::new(DATA);
+        let headers = Headers::parse(&mut reader).unwrap();
+
+        let mut expected = Vec::new();
+        let mut parser = DataParser::new(reader, &headers);
+        parser.record_corruptions();
+        while let Some(event) = parser.next() {
+            if let ParseEvent::Main(main) = event {
+                expected.push(main.iter().collect::<Vec<_>>());
+            }
+        }
+        let expected_stats = parser.stats().clone();
+
+        let mut stream = StreamReader::new(Trickle(DATA));
+        let header_bytes = stream.read_headers().unwrap().unwrap();
+        let headers = Headers::parse(&mut Reader::new(&header_bytes)).unwrap();
+
+        let mut actual = Vec::new();
+        let mut parser = StreamDataParser::new(&mut stream, &headers);
+        parser.record_corruptions();
+        while let Some(event) = parser.next().unwrap() {
+            if let ParseEvent::Main(main) = event {
+                actual.push(main.iter().collect::<Vec<_>>());
+            }
+        }
+        let stats = parser.stats();
+
+        assert_eq!(expected, actual);
+        assert_eq!(expected_stats.counts.event, stats.counts.event);
+        assert_eq!(expected_stats.counts.main, stats.counts.main);
+        assert_eq!(expected_stats.counts.slow, stats.counts.slow);
+        assert_eq!(expected_stats.corrupted.main, stats.corrupted.main);
+        assert_eq!(expected_stats.skipped_bytes, stats.skipped_bytes);
+        assert!(!expected_stats.corruptions.as_ref().unwrap().is_empty());
+        assert_eq!(expected_stats.corruptions, stats.corruptions);
+    }
+
+    /// Collects the main frames and stats from the data section of `log`
+    fn parse_slice(log: &[u8]) -> (Vec<Vec<MainValue>>, Stats) {
+        let mut reader = Reader::new(log);
+        let headers = Headers::parse(&mut reader).unwrap();
+
+        let mut frames = Vec::new();
+        let mut parser = DataParser::new(reader, &headers);
+        while let Some(event) = parser.next() {
+            if let ParseEvent::Main(main) = event {
+                frames.push(main.iter().collect());
+            }
+        }
+
+        (frames, parser.stats().clone())
+    }
+
+    #[test]
+    fn stops_at_next_log() {
+        static GPS: &[u8] = include_bytes!("../tests/logs/gps.bbl");
+
+        // Cut off the first log just before its End event
+        let end = memmem::rfind(GPS, b"E\xffEnd of log").unwrap();
+        let logs = [&GPS[..end], GPS, GPS];
+        let data = logs.concat();
+        assert_eq!(logs.len(), crate::File::new(&data).log_count());
+
+        let mut stream = StreamReader::new(Trickle(&data));
+        for log in logs {
+            let (expected, expected_stats) = parse_slice(log);
+
+            let header_bytes = stream.read_headers().unwrap().unwrap();
+            let headers = Headers::parse(&mut Reader::new(&header_bytes)).unwrap();
+
+            let mut actual = Vec::new();
+            let mut parser = StreamDataParser::new(&mut stream, &headers);
+            while let Some(event) = parser.next().unwrap() {
+                if let ParseEvent::Main(main) = event {
+                    actual.push(main.iter().collect::<Vec<_>>());
+                }
+            }
+            let stats = parser.stats();
+
+            assert_eq!(expected, actual);
+            assert_eq!(expected_stats.counts.main, stats.counts.main);
+            assert_eq!(expected_stats.counts.gps, stats.counts.gps);
+            assert_eq!(expected_stats.skipped_bytes, stats.skipped_bytes);
+        }
+
+        assert!(stream.read_headers().unwrap().is_none());
+    }
+
+    #[test]
+    fn no_logs() {
+        let mut stream = StreamReader::new(Trickle(b"not a blackbox log"));
+        assert!(stream.read_headers().unwrap().is_none());
+    }
+
+    #[test]
+    fn skips_to_marker() {
+        let mut data = b"garbage".to_vec();
+        data.extend_from_slice(DATA);
+
+        let mut stream = StreamReader::new(Trickle(&data));
+        let headers = stream.read_headers().unwrap().unwrap();
+        assert!(headers.starts_with(crate::MARKER));
+        assert!(DATA.starts_with(&headers));
+    }
+}
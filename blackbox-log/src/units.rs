@@ -4,23 +4,24 @@
 use bitvec::prelude::*;
 pub use uom::si;
 pub use uom::si::f64::{
-    Acceleration, AngularVelocity, ElectricCurrent, ElectricPotential, Length, Time, Velocity,
+    Acceleration, AngularVelocity, ElectricCurrent, ElectricPotential, Frequency, Length, Ratio,
+    Time, Velocity,
 };
 
-use crate::headers::FirmwareKind;
+use crate::config::{Axes, Rates, RatesType};
+use crate::headers::{
+    CurrentCalibration, FirmwareKind, FirmwareVersion, MotorOutputRange, VbatCalibration,
+};
 use crate::Headers;
 
 #[allow(unreachable_pub)]
 pub(crate) mod prelude {
     pub use super::si::acceleration::meter_per_second_squared as mps2;
-    pub use super::si::angular_velocity::degree_per_second;
-    pub use super::si::electric_current::{ampere, milliampere};
-    pub use super::si::electric_potential::{millivolt, volt};
     pub use super::si::length::meter;
-    pub use super::si::time::{microsecond, second};
-    pub use super::si::velocity::meter_per_second;
+    pub use super::si::time::microsecond;
     pub use super::{
-        Acceleration, AngularVelocity, ElectricCurrent, ElectricPotential, Length, Time, Velocity,
+        Acceleration, AngularVelocity, ElectricCurrent, ElectricPotential, Frequency, Length,
+        Ratio, Time, Velocity,
     };
 }
 
@@ -28,16 +29,228 @@ mod from_raw {
     #[allow(unreachable_pub)]
     pub trait FromRaw {
         type Raw;
-        fn from_raw(raw: Self::Raw, headers: &super::Headers) -> Self;
+        fn from_raw(raw: Self::Raw, ctx: &super::UnitContext) -> Self;
     }
 }
 
 pub(crate) use from_raw::FromRaw;
 
+/// The values from the [`Headers`] needed to convert raw field values.
+///
+/// This is copied out of the headers so it can be kept by owned frames.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct UnitContext {
+    pub(crate) firmware_kind: FirmwareKind,
+    pub(crate) flag_layout: FlagLayout,
+    pub(crate) acceleration_1g: Option<u16>,
+    pub(crate) gyro_scale: Option<f32>,
+    vbat: VbatScale,
+    amperage: AmperageScale,
+    motor_output: Option<MotorOutputRange>,
+    motor_poles: u8,
+    rates: Option<RateCurve>,
+}
+
+impl UnitContext {
+    pub(crate) fn new(headers: &Headers) -> Self {
+        // Assume anything without a parsable version is recent
+        let since = |major, minor, patch| {
+            headers
+                .firmware_version
+                .map_or(true, |version| version.is_at_least(major, minor, patch))
+        };
+
+        let vbat_adc = VbatScale::Adc(headers.vbat_calibration.unwrap_or_default());
+        let amperage_adc = AmperageScale::Adc(headers.current_calibration.unwrap_or_default());
+
+        let (vbat, amperage) = match headers.firmware_kind {
+            FirmwareKind::Betaflight => {
+                let vbat = if since(4, 0, 0) {
+                    VbatScale::Centivolts
+                } else if since(3, 1, 0) {
+                    VbatScale::Decivolts
+                } else {
+                    vbat_adc
+                };
+
+                let amperage = if since(3, 1, 0) {
+                    AmperageScale::Centiamps
+                } else {
+                    amperage_adc
+                };
+
+                (vbat, amperage)
+            }
+            FirmwareKind::Cleanflight if since(2, 0, 0) => {
+                (VbatScale::Decivolts, AmperageScale::Centiamps)
+            }
+            FirmwareKind::Inav | FirmwareKind::Cleanflight if !since(2, 0, 0) => {
+                (vbat_adc, amperage_adc)
+            }
+            FirmwareKind::Inav
+            | FirmwareKind::Cleanflight
+            | FirmwareKind::EmuFlight
+            | FirmwareKind::Rotorflight
+            | FirmwareKind::Unknown => (VbatScale::Centivolts, AmperageScale::Centiamps),
+        };
+
+        Self {
+            firmware_kind: headers.firmware_kind,
+            flag_layout: FlagLayout::new(headers.firmware_kind, headers.firmware_version.as_ref()),
+            acceleration_1g: headers.acceleration_1g,
+            gyro_scale: headers.gyro_scale,
+            vbat,
+            amperage,
+            motor_output: headers.motor_output_range,
+            motor_poles: headers.motor_poles.unwrap_or(DEFAULT_MOTOR_POLES),
+            rates: RateCurve::new(&headers.config.rates, headers.firmware_kind),
+        }
+    }
+}
+
+/// How `vbatLatest` is logged. Older firmware logged the raw ADC reading.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum VbatScale {
+    Adc(VbatCalibration),
+    Decivolts,
+    Centivolts,
+}
+
+/// How `amperageLatest` is logged. Older firmware logged the raw ADC reading.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum AmperageScale {
+    Adc(CurrentCalibration),
+    Centiamps,
+}
+
+/// ADC readings are 12 bits, with a 3.3V reference.
+const ADC_MAX: f64 = 4095.;
+const ADC_REFERENCE_MILLIVOLTS: f64 = 3300.;
+
+/// Returns the voltage at the ADC pin for a raw reading, in millivolts.
+fn adc_millivolts(raw: f64) -> f64 {
+    raw * ADC_REFERENCE_MILLIVOLTS / ADC_MAX
+}
+
+/// Default for a missing `motor_poles` header, matching Betaflight.
+const DEFAULT_MOTOR_POLES: u8 = 14;
+
+/// The default `rate_limits` in Betaflight.
+const DEFAULT_RATE_LIMIT: u16 = 1998;
+
+/// The curve that converts `rcCommand` roll, pitch & yaw into the requested
+/// rotation rate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum RateCurve {
+    /// INAV applies expo before logging `rcCommand`, leaving only a linear
+    /// scale.
+    Inav { rates: Axes<u8> },
+    Betaflight {
+        kind: RatesType,
+        rc_rates: Axes<u8>,
+        rc_expo: Axes<u8>,
+        rates: Axes<u8>,
+        limits: Axes<u16>,
+    },
+}
+
+impl RateCurve {
+    /// Returns `None` if any of the needed rates are missing.
+    pub(crate) fn new(rates: &Rates, firmware: FirmwareKind) -> Option<Self> {
+        if firmware == FirmwareKind::Inav {
+            return Some(Self::Inav {
+                rates: rates.rates?,
+            });
+        }
+
+        // `rates_type` was added in Betaflight 4.2, before which there were only
+        // Betaflight rates
+        let kind = rates.kind.unwrap_or(RatesType::Betaflight);
+        if let RatesType::Unknown(_) = kind {
+            return None;
+        }
+
+        Some(Self::Betaflight {
+            kind,
+            rc_rates: rates.rc_rates?,
+            rc_expo: rates.rc_expo?,
+            rates: rates.rates?,
+            limits: rates.rate_limits.unwrap_or(Axes {
+                roll: DEFAULT_RATE_LIMIT,
+                pitch: DEFAULT_RATE_LIMIT,
+                yaw: DEFAULT_RATE_LIMIT,
+            }),
+        })
+    }
+
+    /// Returns the rotation rate in degrees / second for a raw `rcCommand`
+    /// value, which ranges from -500 to 500.
+    fn degrees_per_second(self, axis: usize, raw: i32) -> f64 {
+        let command = (f64::from(raw) / 500.).clamp(-1., 1.);
+
+        let (kind, rc_rates, rc_expo, rates, limits) = match self {
+            Self::Inav { rates } => return command * f64::from(rates.get(axis)) * 10.,
+            Self::Betaflight {
+                kind,
+                rc_rates,
+                rc_expo,
+                rates,
+                limits,
+            } => (kind, rc_rates, rc_expo, rates, limits),
+        };
+
+        let rc_rate = f64::from(rc_rates.get(axis));
+        let expo = f64::from(rc_expo.get(axis)) / 100.;
+        let rate = f64::from(rates.get(axis));
+        let limit = f64::from(limits.get(axis));
+
+        // `f64::abs` and `f64::powi` need `std`
+        let abs = if command < 0. { -command } else { command };
+        let cube = |x: f64| x * x * x;
+        let super_factor = |x: f64| 1. / (1. - x).clamp(0.01, 1.);
+
+        let degrees = match kind {
+            RatesType::Betaflight => {
+                let command = command * cube(abs) * expo + command * (1. - expo);
+                let mut rc_rate = rc_rate / 100.;
+                if rc_rate > 2. {
+                    rc_rate += 14.54 * (rc_rate - 2.);
+                }
+
+                200. * rc_rate * command * super_factor(abs * rate / 100.)
+            }
+            RatesType::Raceflight => {
+                let command = (1. + expo * (command * command - 1.)) * command;
+                10. * rc_rate * command * (1. + abs * rate / 100.)
+            }
+            RatesType::Kiss => {
+                let command = cube(command) * expo + command * (1. - expo);
+                2. * rc_rate * command * super_factor(abs * rate / 100.)
+            }
+            RatesType::Actual => {
+                let expo = abs * (cube(command) * command * command * expo + command * (1. - expo));
+                let center = rc_rate * 10.;
+                let stick = (rate * 10. - center).max(0.);
+                command * center + stick * expo
+            }
+            RatesType::Quick if rc_rate == 0. => 0.,
+            RatesType::Quick => {
+                let rc_rate = rc_rate * 2.;
+                let max = (rate * 10.).max(rc_rate) / rc_rate;
+                let curve = cube(abs) * expo + abs * (1. - expo);
+                command * rc_rate * super_factor(curve * (max - 1.) / max)
+            }
+            RatesType::Unknown(_) => unreachable!(),
+        };
+
+        degrees.clamp(-limit, limit)
+    }
+}
+
 impl FromRaw for Time {
     type Raw = u64;
 
-    fn from_raw(raw: Self::Raw, _headers: &Headers) -> Self {
+    fn from_raw(raw: Self::Raw, _ctx: &UnitContext) -> Self {
         Self::new::<prelude::microsecond>(raw as f64)
     }
 }
@@ -45,11 +258,11 @@ fn from_raw(raw: Self::Raw, _headers: &Headers) -> Self {
 impl FromRaw for Acceleration {
     type Raw = i32;
 
-    fn from_raw(raw: Self::Raw, headers: &Headers) -> Self {
+    fn from_raw(raw: Self::Raw, ctx: &UnitContext) -> Self {
         // TODO: switch to `standard_gravity` instead of `mps2` once
         // https://github.com/iliekturtles/uom/pull/351 lands
 
-        let gs = f64::from(raw) / f64::from(headers.acceleration_1g.unwrap());
+        let gs = f64::from(raw) / f64::from(ctx.acceleration_1g.unwrap());
         Self::new::<prelude::mps2>(gs * 9.80665)
     }
 }
@@ -57,23 +270,38 @@ fn from_raw(raw: Self::Raw, headers: &Headers) -> Self {
 impl FromRaw for AngularVelocity {
     type Raw = i32;
 
-    fn from_raw(raw: Self::Raw, headers: &Headers) -> Self {
-        let scale = headers.gyro_scale.unwrap();
+    fn from_raw(raw: Self::Raw, ctx: &UnitContext) -> Self {
+        let scale = ctx.gyro_scale.unwrap();
         let rad = f64::from(scale) * f64::from(raw);
 
         AngularVelocity::new::<si::angular_velocity::radian_per_second>(rad)
     }
 }
 
+impl FromRaw for Frequency {
+    type Raw = i32;
+
+    fn from_raw(raw: Self::Raw, _ctx: &UnitContext) -> Self {
+        Self::new::<si::frequency::hertz>(raw.into())
+    }
+}
+
 impl FromRaw for ElectricCurrent {
     type Raw = i32;
 
-    fn from_raw(raw: Self::Raw, _headers: &Headers) -> Self {
-        new_amps(raw)
+    fn from_raw(raw: Self::Raw, ctx: &UnitContext) -> Self {
+        match ctx.amperage {
+            AmperageScale::Centiamps => new_amps(raw),
+            AmperageScale::Adc(calibration) => {
+                let millivolts = adc_millivolts(raw.into()) - f64::from(calibration.offset);
+                let milliamps = millivolts * 10_000. / f64::from(calibration.scale);
+                Self::new::<si::electric_current::milliampere>(milliamps)
+            }
+        }
     }
 }
 
-/// Correct from BF 3.1.7 (3.1.0?), INAV 2.0.0
+/// Correct from BF 3.1.0, INAV 2.0.0
 #[inline(always)]
 fn new_amps(raw: i32) -> ElectricCurrent {
     ElectricCurrent::new::<si::electric_current::centiampere>(raw.into())
@@ -82,21 +310,72 @@ fn new_amps(raw: i32) -> ElectricCurrent {
 impl FromRaw for ElectricPotential {
     type Raw = u32;
 
-    fn from_raw(raw: Self::Raw, _headers: &Headers) -> Self {
-        new_vbat(raw)
+    fn from_raw(raw: Self::Raw, ctx: &UnitContext) -> Self {
+        match ctx.vbat {
+            VbatScale::Centivolts => new_vbat(raw),
+            VbatScale::Decivolts => Self::new::<si::electric_potential::decivolt>(raw.into()),
+            VbatScale::Adc(calibration) => {
+                let millivolts = adc_millivolts(raw.into()) * f64::from(calibration.scale) / 10.;
+                Self::new::<si::electric_potential::millivolt>(millivolts)
+            }
+        }
     }
 }
 
-/// Correct from BF 4.0.0, INAV 3.0.0?
+/// Correct from BF 4.0.0, INAV 2.0.0
 #[inline(always)]
 fn new_vbat(raw: u32) -> ElectricPotential {
     ElectricPotential::new::<si::electric_potential::centivolt>(raw.into())
 }
 
+impl FromRaw for Length {
+    type Raw = i32;
+
+    fn from_raw(raw: Self::Raw, _ctx: &UnitContext) -> Self {
+        Self::new::<si::length::centimeter>(raw.into())
+    }
+}
+
+/// Converts a `motor[n]` output into a fraction of the `motorOutput` range.
+pub(crate) fn new_motor_output(raw: u32, ctx: &UnitContext) -> Ratio {
+    let range = ctx.motor_output.unwrap();
+    let min = f64::from(range.min);
+    let max = f64::from(range.max);
+
+    Ratio::new::<si::ratio::ratio>((f64::from(raw) - min) / (max - min))
+}
+
+/// Converts an `rcCommand[n]` for roll, pitch or yaw into the requested
+/// rotation rate using the configured rates.
+pub(crate) fn new_rc_command(raw: i32, axis: usize, ctx: &UnitContext) -> AngularVelocity {
+    let degrees = ctx.rates.unwrap().degrees_per_second(axis, raw);
+    AngularVelocity::new::<si::angular_velocity::degree_per_second>(degrees)
+}
+
+/// Converts a `setpoint[n]` for roll, pitch or yaw, which is logged in
+/// degrees / second.
+pub(crate) fn new_setpoint(raw: i32) -> AngularVelocity {
+    AngularVelocity::new::<si::angular_velocity::degree_per_second>(raw.into())
+}
+
+/// Converts an `eRPM[n]`, which is logged in hundreds of electrical RPM, into
+/// the rotation frequency of the motor.
+pub(crate) fn new_erpm(raw: u32, ctx: &UnitContext) -> Frequency {
+    let pole_pairs = f64::from(ctx.motor_poles.max(2) / 2);
+    let rpm = f64::from(raw) * 100. / pole_pairs;
+
+    Frequency::new::<si::frequency::hertz>(rpm / 60.)
+}
+
+/// Converts `rssi`, which ranges from 0 to 1023.
+pub(crate) fn new_rssi(raw: u32) -> Ratio {
+    Ratio::new::<si::ratio::ratio>(f64::from(raw) / 1023.)
+}
+
 impl FromRaw for Velocity {
     type Raw = u32;
 
-    fn from_raw(raw: Self::Raw, _headers: &Headers) -> Self {
+    fn from_raw(raw: Self::Raw, _ctx: &UnitContext) -> Self {
         Self::new::<si::velocity::centimeter_per_second>(raw.into())
     }
 }
@@ -116,21 +395,66 @@ pub trait Flag {
     fn as_name(&self) -> &'static str;
 }
 
+/// The bit layout used by [`FlightModeSet`] & [`StateSet`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) enum FlagLayout {
+    Betaflight,
+    Inav,
+    /// Betaflight 3.x, Cleanflight 2.x & EmuFlight
+    Legacy,
+    /// Cleanflight 1.x, which logs `flightModeFlags` instead of the active box
+    /// IDs
+    Cleanflight1,
+}
+
+impl FlagLayout {
+    pub(crate) fn new(firmware: FirmwareKind, version: Option<&FirmwareVersion>) -> Self {
+        // Rotorflight & unknown firmware are assumed to match Betaflight, and
+        // anything without a parsable version to be recent
+        let before = |major| version.map_or(false, |version| !version.is_at_least(major, 0, 0));
+        let legacy = before(4);
+
+        match firmware {
+            FirmwareKind::Inav => Self::Inav,
+            FirmwareKind::Cleanflight if before(2) => Self::Cleanflight1,
+            FirmwareKind::Cleanflight | FirmwareKind::EmuFlight => Self::Legacy,
+            FirmwareKind::Betaflight | FirmwareKind::Unknown if legacy => Self::Legacy,
+            FirmwareKind::Betaflight | FirmwareKind::Unknown | FirmwareKind::Rotorflight => {
+                Self::Betaflight
+            }
+        }
+    }
+}
+
 macro_rules! define_flag_set {
+    // Cleanflight 1.x uses the legacy bits, unless it has its own column
+    ($(#[$set_attr:meta])* $set:ident, $(#[$flag_attr:meta])* $flag_name:ident {
+        $( $flag:ident : $($beta:literal)? / $($inav:literal)? / $($legacy:literal)? ),* $(,)?
+    }) => {
+        define_flag_set! {
+            $(#[$set_attr])* $set, $(#[$flag_attr])* $flag_name {
+                $( $flag : $($beta)? / $($inav)? / $($legacy)? / $($legacy)? ),*
+            }
+        }
+    };
+
     ($(#[$set_attr:meta])* $set:ident, $(#[$flag_attr:meta])* $flag_name:ident {
-        $( $flag:ident : $($beta:literal)? / $($inav:literal)? ),* $(,)?
+        $(
+            $flag:ident :
+            $($beta:literal)? / $($inav:literal)? / $($legacy:literal)? / $($cf1:literal)?
+        ),* $(,)?
     }) => {
         #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
         $(#[$set_attr])*
         pub struct $set {
-            firmware: FirmwareKind,
+            layout: FlagLayout,
             raw: BitArray<[u32; 1], Lsb0>,
         }
 
         impl $set {
-            pub(crate) fn new(raw: u32, firmware: FirmwareKind) -> Self {
+            pub(crate) fn new(raw: u32, layout: FlagLayout) -> Self {
                 Self {
-                    firmware,
+                    layout,
                     raw: BitArray::new([raw]),
                 }
             }
@@ -140,14 +464,14 @@ impl FlagSet for $set {
             type Flag = $flag_name;
 
             fn is_set(&self, flag: Self::Flag) -> bool {
-                flag.to_bit(self.firmware)
+                flag.to_bit(self.layout)
                     .map_or(false, |bit| self.raw[bit])
             }
 
             fn as_names(&self) -> Vec<&'static str> {
                 self.raw
                     .iter_ones()
-                    .filter_map(|bit| Some($flag_name::from_bit(bit, self.firmware)?.as_name()))
+                    .filter_map(|bit| Some($flag_name::from_bit(bit, self.layout)?.as_name()))
                     .collect()
             }
         }
@@ -158,6 +482,13 @@ fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
             }
         }
 
+        #[cfg(feature = "serde")]
+        impl serde::Serialize for $set {
+            fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serializer.collect_seq(self.as_names())
+            }
+        }
+
         #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
         $(#[$flag_attr])*
         pub enum $flag_name {
@@ -173,18 +504,22 @@ fn as_name(&self) -> &'static str {
         }
 
         impl $flag_name {
-            const fn from_bit(bit: usize, firmware: FirmwareKind) -> Option<Self> {
-                match (bit, firmware) {
-                    $($( ($beta, FirmwareKind::Betaflight | FirmwareKind::EmuFlight) => Some(Self::$flag), )?)*
-                    $($( ($inav, FirmwareKind::Inav) => Some(Self::$flag), )?)*
+            const fn from_bit(bit: usize, layout: FlagLayout) -> Option<Self> {
+                match (bit, layout) {
+                    $($( ($beta, FlagLayout::Betaflight) => Some(Self::$flag), )?)*
+                    $($( ($inav, FlagLayout::Inav) => Some(Self::$flag), )?)*
+                    $($( ($legacy, FlagLayout::Legacy) => Some(Self::$flag), )?)*
+                    $($( ($cf1, FlagLayout::Cleanflight1) => Some(Self::$flag), )?)*
                     _ => None,
                 }
             }
 
-            const fn to_bit(self, firmware: FirmwareKind) -> Option<usize> {
-                match (self, firmware) {
-                    $($( (Self::$flag, FirmwareKind::Betaflight | FirmwareKind::EmuFlight) => Some($beta), )?)*
-                    $($( (Self::$flag, FirmwareKind::Inav) => Some($inav), )?)*
+            const fn to_bit(self, layout: FlagLayout) -> Option<usize> {
+                match (self, layout) {
+                    $($( (Self::$flag, FlagLayout::Betaflight) => Some($beta), )?)*
+                    $($( (Self::$flag, FlagLayout::Inav) => Some($inav), )?)*
+                    $($( (Self::$flag, FlagLayout::Legacy) => Some($legacy), )?)*
+                    $($( (Self::$flag, FlagLayout::Cleanflight1) => Some($cf1), )?)*
                     _ => None,
                 }
             }
@@ -200,43 +535,48 @@ fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
 
 define_flag_set! {
     /// All currently enabled flight modes. See [`FlagSet`] and [`FlightMode`].
+    ///
+    /// Only the first 32 Betaflight box IDs are logged, so later modes such as
+    /// launch control are never included.
     FlightModeSet,
     /// A flight mode. See [`Flag`].
     FlightMode {
-        Angle:     1 /  0,
-        Horizon:   2 /  1,
-        HeadFree:  4 /  6,
-        Failsafe:  6 /  9,
-        Turtle:   27 / 15,
-
-        Arm:            0 / ,
-        Mag:            3 / ,
-        Passthru:       5 / ,
-        GpsRescue:      7 / ,
-        Antigravity:    8 / ,
-        HeadAdjust:     9 / ,
-        CamStab:       10 / ,
-        BeeperOn:      11 / ,
-        LedLow:        12 / ,
-        Calib:         13 / ,
-        Osd:           14 / ,
-        Telemetry:     15 / ,
-        Servo1:        16 / ,
-        Servo2:        17 / ,
-        Servo3:        18 / ,
-        Blackbox:      19 / ,
-        Airmode:       20 / ,
-        ThreeD:        21 / ,
-        FpvAngleMix:   22 / ,
-        BlackboxErase: 23 / ,
-        Camera1:       24 / ,
-        Camera2:       25 / ,
-        Camera3:       26 / ,
-        Prearm:        28 / ,
-        BeepGpsCount:  29 / ,
-        VtxPitmode:    30 / ,
-        Paralyze:      31 / ,
-
+        Angle:     1 /  0 / 1 / 0,
+        Horizon:   2 /  1 / 2 / 1,
+        HeadFree:  4 /  6 / / 6,
+        Failsafe:  6 /  9 / / 10,
+        Turtle:   27 / 15 / / ,
+
+        Arm:            0 / / 0 / ,
+        Mag:            3 / / / 2,
+        Passthru:       5 / / / 8,
+        GpsRescue:      7 / / / ,
+        Antigravity:    8 / / / ,
+        HeadAdjust:     9 / / / ,
+        CamStab:       10 / / / ,
+        BeeperOn:      11 / / / ,
+        LedLow:        12 / / / ,
+        Calib:         13 / / / ,
+        Osd:           14 / / / ,
+        Telemetry:     15 / / / ,
+        Servo1:        16 / / / ,
+        Servo2:        17 / / / ,
+        Servo3:        18 / / / ,
+        Blackbox:      19 / / / ,
+        Airmode:       20 / / / ,
+        ThreeD:        21 / / / ,
+        FpvAngleMix:   22 / / / ,
+        BlackboxErase: 23 / / / ,
+        Camera1:       24 / / / ,
+        Camera2:       25 / / / ,
+        Camera3:       26 / / / ,
+        Prearm:        28 / / / ,
+        BeepGpsCount:  29 / / / ,
+        VtxPitmode:    30 / / / ,
+        Paralyze:      31 / / / ,
+
+        // Box IDs 32 and up are never logged: both the `flightModeFlags` slow field
+        // and flight mode events only hold the low 32 bits
         // User1:               32 / ,
         // User2:               33 / ,
         // User3:               34 / ,
@@ -249,18 +589,22 @@ fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         // StickCommandDisable: 41 / ,
         // BeeperMute:          42 / ,
 
-        Heading:       /  2,
-        NavAltHold:    /  3,
-        NavRth:        /  4,
-        NavPoshold:    /  5,
-        NavLaunch:     /  7,
-        Manual:        /  8,
-        AutoTune:      / 10,
-        NavWp:         / 11,
-        NavCourseHold: / 12,
-        Flaperon:      / 13,
-        TurnAssistant: / 14,
-        Soaring:       / 16,
+        // Betaflight 3.x, Cleanflight 2.x & EmuFlight have BARO at 3, and only box
+        // IDs 0 to 3 are known to match. Cleanflight 1.x logs `flightModeFlags_e`.
+        Baro:            / / 3 / 3,
+
+        Heading:       /  2 / / ,
+        NavAltHold:    /  3 / / ,
+        NavRth:        /  4 / / ,
+        NavPoshold:    /  5 / / ,
+        NavLaunch:     /  7 / / ,
+        Manual:        /  8 / / ,
+        AutoTune:      / 10 / / 7,
+        NavWp:         / 11 / / ,
+        NavCourseHold: / 12 / / ,
+        Flaperon:      / 13 / / ,
+        TurnAssistant: / 14 / / ,
+        Soaring:       / 16 / / ,
     }
 }
 
@@ -269,34 +613,41 @@ fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
     StateSet,
     /// A flight controller state. See [`Flag`].
     State {
-        GpsFixHome: 0 / 0,
-        GpsFix:     1 / 1,
-        GpsFixEver: 2 /  ,
-
-        CalibrateMag:                 /  2,
-        SmallAngle:                   /  3,
-        FixedWingLegacy:              /  4,
-        AntiWindup:                   /  5,
-        FlaperonAvailable:            /  6,
-        NavMotorStopOrIdle:           /  7,
-        CompassCalibrated:            /  8,
-        AccelerometerCalibrated:      /  9,
-        NavCruiseBraking:             / 11,
-        NavCruiseBrakingBoost:        / 12,
-        NavCruiseBrakingLocked:       / 13,
-        NavExtraArmingSafetyBypassed: / 14,
-        AirmodeActive:                / 15,
-        EscSensorEnabled:             / 16,
-        Airplane:                     / 17,
-        Multirotor:                   / 18,
-        Rover:                        / 19,
-        Boat:                         / 20,
-        AltitudeControl:              / 21,
-        MoveForwardOnly:              / 22,
-        SetReversibleMotorsForward:   / 23,
-        FwHeadingUseYaw:              / 24,
-        AntiWindupDeactivated:        / 25,
-        LandingDetected:              / 26,
+        GpsFixHome:      0 /  0 / 0,
+        GpsFix:          1 /  1 / 1,
+        GpsFixEver:      2 /    /  ,
+        CalibrateMag:      /  2 / 2,
+        SmallAngle:        /  3 / 3,
+        FixedWingLegacy:   /  4 / 4,
+
+        AntiWindup:                   /  5 / ,
+        FlaperonAvailable:            /  6 / ,
+        NavMotorStopOrIdle:           /  7 / ,
+        CompassCalibrated:            /  8 / ,
+        AccelerometerCalibrated:      /  9 / ,
+        NavCruiseBraking:             / 11 / ,
+        NavCruiseBrakingBoost:        / 12 / ,
+        NavCruiseBrakingLocked:       / 13 / ,
+        NavExtraArmingSafetyBypassed: / 14 / ,
+        AirmodeActive:                / 15 / ,
+        EscSensorEnabled:             / 16 / ,
+        Airplane:                     / 17 / ,
+        Multirotor:                   / 18 / ,
+        Rover:                        / 19 / ,
+        Boat:                         / 20 / ,
+        AltitudeControl:              / 21 / ,
+        MoveForwardOnly:              / 22 / ,
+        SetReversibleMotorsForward:   / 23 / ,
+        FwHeadingUseYaw:              / 24 / ,
+        AntiWindupDeactivated:        / 25 / ,
+        LandingDetected:              / 26 / ,
+    }
+}
+
+impl FlightModeSet {
+    /// Returns the flags as logged in flight mode events.
+    pub(crate) fn to_raw(self) -> u32 {
+        self.raw.into_inner()[0]
     }
 }
 
@@ -316,6 +667,8 @@ pub enum FailsafePhase {
 }
 
 impl FailsafePhase {
+    // Every firmware other than INAV is assumed to use Betaflight's phases. This
+    // has not been checked against Cleanflight, EmuFlight or Rotorflight.
     pub(crate) fn new(raw: u32, firmware: FirmwareKind) -> Self {
         let mapping: &[Self] = if firmware == FirmwareKind::Inav {
             &[
@@ -374,8 +727,113 @@ fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
     }
 }
 
+/// Why the craft was disarmed. See [`Flag`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum DisarmReason {
+    ArmingDisabled,
+    Failsafe,
+    ThrottleTimeout,
+    Sticks,
+    Switch,
+    Switch3d,
+    KillSwitch,
+    CrashProtection,
+    RunawayTakeoff,
+    GpsRescue,
+    SerialCommand,
+    Navigation,
+    Landing,
+    /// INAV logs this when the craft is not disarmed yet.
+    None,
+    /// A reason not known for the firmware, with its raw value.
+    Unknown(u32),
+}
+
+impl DisarmReason {
+    pub(crate) fn new(raw: u32, firmware: FirmwareKind) -> Self {
+        usize::try_from(raw)
+            .ok()
+            .and_then(|index| Self::mapping(firmware).get(index))
+            .copied()
+            .unwrap_or_else(|| {
+                tracing::debug!("invalid disarm reason ({raw})");
+                Self::Unknown(raw)
+            })
+    }
+
+    pub(crate) fn to_raw(self, firmware: FirmwareKind) -> Option<u32> {
+        if let Self::Unknown(raw) = self {
+            return Some(raw);
+        }
+
+        let index = Self::mapping(firmware).iter().position(|&r| r == self)?;
+        u32::try_from(index).ok()
+    }
+
+    // As with `FailsafePhase`, everything other than INAV is assumed to use
+    // Betaflight's reasons
+    fn mapping(firmware: FirmwareKind) -> &'static [Self] {
+        if firmware == FirmwareKind::Inav {
+            &[
+                Self::None,
+                Self::ThrottleTimeout,
+                Self::Sticks,
+                Self::Switch3d,
+                Self::Switch,
+                Self::KillSwitch,
+                Self::Failsafe,
+                Self::Navigation,
+                Self::Landing,
+            ]
+        } else {
+            &[
+                Self::ArmingDisabled,
+                Self::Failsafe,
+                Self::ThrottleTimeout,
+                Self::Sticks,
+                Self::Switch,
+                Self::CrashProtection,
+                Self::RunawayTakeoff,
+                Self::GpsRescue,
+                Self::SerialCommand,
+            ]
+        }
+    }
+}
+
+impl Flag for DisarmReason {
+    fn as_name(&self) -> &'static str {
+        match self {
+            Self::ArmingDisabled => "ArmingDisabled",
+            Self::Failsafe => "Failsafe",
+            Self::ThrottleTimeout => "ThrottleTimeout",
+            Self::Sticks => "Sticks",
+            Self::Switch => "Switch",
+            Self::Switch3d => "Switch3d",
+            Self::KillSwitch => "KillSwitch",
+            Self::CrashProtection => "CrashProtection",
+            Self::RunawayTakeoff => "RunawayTakeoff",
+            Self::GpsRescue => "GpsRescue",
+            Self::SerialCommand => "SerialCommand",
+            Self::Navigation => "Navigation",
+            Self::Landing => "Landing",
+            Self::None => "None",
+            Self::Unknown(_) => "Unknown",
+        }
+    }
+}
+
+impl fmt::Display for DisarmReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_name())
+    }
+}
+
 #[cfg(test)]
 mod tests {
+    use test_case::case;
+
     use super::*;
 
     macro_rules! float_eq {
@@ -391,14 +849,205 @@ macro_rules! float_eq {
         };
     }
 
+    #[case(FirmwareKind::Betaflight, 0, DisarmReason::ArmingDisabled; "betaflight arming disabled")]
+    #[case(FirmwareKind::Betaflight, 6, DisarmReason::RunawayTakeoff; "betaflight runaway takeoff")]
+    #[case(FirmwareKind::EmuFlight, 4, DisarmReason::Switch; "emuflight switch")]
+    #[case(FirmwareKind::Inav, 4, DisarmReason::Switch; "inav switch")]
+    #[case(FirmwareKind::Inav, 8, DisarmReason::Landing; "inav landing")]
+    #[case(FirmwareKind::Inav, 9, DisarmReason::Unknown(9); "inav unknown")]
+    fn disarm_reason(firmware: FirmwareKind, raw: u32, expected: DisarmReason) {
+        let reason = DisarmReason::new(raw, firmware);
+        assert_eq!(expected, reason);
+        assert_eq!(Some(raw), reason.to_raw(firmware));
+    }
+
+    fn context(revision: &str, headers: &[(&str, &str)]) -> UnitContext {
+        let log = crate::utils::log_with_headers(revision, headers);
+        let headers = Headers::parse(&mut crate::Reader::new(&log)).unwrap();
+        UnitContext::new(&headers)
+    }
+
+    #[case("Betaflight 4.2.11 (948ba6339) STM32F7X2", 1632, 16.32; "betaflight 4")]
+    #[case("Betaflight 3.5.7 (6e1ab5ba3) STM32F405", 163, 16.3; "betaflight 3.5")]
+    #[case("Betaflight 3.0.1 (1234567) NAZE", 2048, 18.1544; "betaflight adc")]
+    #[case("INAV 5.0.0 (6e52a8a7) MATEKF722SE", 1632, 16.32; "inav")]
+    #[case("INAV 1.9.1 (8c5ac1a6) SPRACINGF3", 2048, 18.1544; "inav adc")]
+    #[case("EmuFlight 0.4.1 (1234567) STM32F7X2", 1632, 16.32; "emuflight")]
+    #[case("Betaflight master (1234567) STM32F405", 1632, 16.32; "unknown version")]
+    fn vbat_scaling(revision: &str, raw: u32, volts: f64) {
+        let vbat = ElectricPotential::from_raw(raw, &context(revision, &[]));
+        float_eq!(volts, vbat.get::<si::electric_potential::volt>());
+    }
+
+    #[case("Betaflight 4.2.11 (948ba6339) STM32F7X2", 139, 1.39; "betaflight")]
+    #[case("Betaflight 3.0.1 (1234567) NAZE", 124, 2.4982; "betaflight adc")]
+    #[case("INAV 1.9.1 (8c5ac1a6) SPRACINGF3", 124, 2.4982; "inav adc")]
+    fn amperage_scaling(revision: &str, raw: i32, amps: f64) {
+        let amperage = ElectricCurrent::from_raw(raw, &context(revision, &[]));
+        float_eq!(amps, amperage.get::<si::electric_current::ampere>());
+    }
+
+    #[case("Betaflight 4.2.11 (948ba6339) STM32F7X2", FlagLayout::Betaflight; "betaflight")]
+    #[case("Betaflight 3.5.7 (6e1ab5ba3) STM32F405", FlagLayout::Legacy; "betaflight 3")]
+    #[case("Betaflight master (1234567) STM32F405", FlagLayout::Betaflight; "unknown version")]
+    #[case("INAV 5.0.0 (6e52a8a7) MATEKF722SE", FlagLayout::Inav; "inav")]
+    #[case("EmuFlight 0.4.1 (1234567) STM32F7X2", FlagLayout::Legacy; "emuflight")]
+    #[case("Cleanflight 2.5.0 (8a6f5a9f1) SPRACINGF3", FlagLayout::Legacy; "cleanflight")]
+    #[case("Cleanflight 1.14.2 (bf4e5e9) NAZE", FlagLayout::Cleanflight1; "cleanflight 1")]
+    #[case("Rotorflight 4.3.0 (20a2ed07f) STM32F7X2", FlagLayout::Betaflight; "rotorflight")]
+    fn flag_layout(revision: &str, expected: FlagLayout) {
+        assert_eq!(expected, context(revision, &[]).flag_layout);
+    }
+
+    #[case(FlagLayout::Betaflight, 1, Some(FlightMode::Angle); "betaflight")]
+    #[case(FlagLayout::Betaflight, 3, Some(FlightMode::Mag); "betaflight mag")]
+    #[case(FlagLayout::Inav, 0, Some(FlightMode::Angle); "inav")]
+    #[case(FlagLayout::Inav, 31, None; "inav missing")]
+    #[case(FlagLayout::Legacy, 1, Some(FlightMode::Angle); "legacy")]
+    #[case(FlagLayout::Legacy, 3, Some(FlightMode::Baro); "legacy baro")]
+    #[case(FlagLayout::Legacy, 4, None; "legacy unknown")]
+    // Cleanflight 1.x: src/main/config/runtime_config.h, flightModeFlags_e
+    #[case(FlagLayout::Cleanflight1, 0, Some(FlightMode::Angle); "cleanflight 1 angle")]
+    #[case(FlagLayout::Cleanflight1, 2, Some(FlightMode::Mag); "cleanflight 1 mag")]
+    #[case(FlagLayout::Cleanflight1, 3, Some(FlightMode::Baro); "cleanflight 1 baro")]
+    #[case(FlagLayout::Cleanflight1, 10, Some(FlightMode::Failsafe); "cleanflight 1 failsafe")]
+    fn flight_mode_bit(layout: FlagLayout, bit: usize, expected: Option<FlightMode>) {
+        assert_eq!(expected, FlightMode::from_bit(bit, layout));
+        if let Some(mode) = expected {
+            assert_eq!(Some(bit), mode.to_bit(layout));
+        }
+    }
+
+    // Betaflight 4.x: src/main/fc/runtime_config.h, stateFlags_t
+    #[case(FlagLayout::Betaflight, 0, Some(State::GpsFixHome); "betaflight gps fix home")]
+    #[case(FlagLayout::Betaflight, 1, Some(State::GpsFix); "betaflight gps fix")]
+    #[case(FlagLayout::Betaflight, 2, Some(State::GpsFixEver); "betaflight gps fix ever")]
+    #[case(FlagLayout::Betaflight, 3, None; "betaflight missing")]
+    // Betaflight 3.x, EmuFlight & Cleanflight: src/main/fc/runtime_config.h, stateFlags_t
+    #[case(FlagLayout::Legacy, 0, Some(State::GpsFixHome); "legacy gps fix home")]
+    #[case(FlagLayout::Legacy, 1, Some(State::GpsFix); "legacy gps fix")]
+    #[case(FlagLayout::Legacy, 2, Some(State::CalibrateMag); "legacy calibrate mag")]
+    #[case(FlagLayout::Legacy, 3, Some(State::SmallAngle); "legacy small angle")]
+    #[case(FlagLayout::Legacy, 4, Some(State::FixedWingLegacy); "legacy fixed wing")]
+    #[case(FlagLayout::Legacy, 5, None; "legacy missing")]
+    #[case(FlagLayout::Cleanflight1, 3, Some(State::SmallAngle); "cleanflight 1")]
+    // INAV: src/main/fc/runtime_config.h, stateFlags_t
+    #[case(FlagLayout::Inav, 0, Some(State::GpsFixHome); "inav gps fix home")]
+    #[case(FlagLayout::Inav, 2, Some(State::CalibrateMag); "inav calibrate mag")]
+    #[case(FlagLayout::Inav, 10, None; "inav missing")]
+    #[case(FlagLayout::Inav, 26, Some(State::LandingDetected); "inav landing detected")]
+    fn state_bit(layout: FlagLayout, bit: usize, expected: Option<State>) {
+        assert_eq!(expected, State::from_bit(bit, layout));
+        if let Some(state) = expected {
+            assert_eq!(Some(bit), state.to_bit(layout));
+        }
+    }
+
+    #[test]
+    fn state_set() {
+        let raw = 0b101;
+
+        let betaflight = StateSet::new(raw, FlagLayout::Betaflight);
+        assert!(betaflight.is_set(State::GpsFixHome));
+        assert!(betaflight.is_set(State::GpsFixEver));
+        assert!(!betaflight.is_set(State::CalibrateMag));
+        assert_eq!(vec!["GpsFixHome", "GpsFixEver"], betaflight.as_names());
+
+        let legacy = StateSet::new(raw, FlagLayout::Legacy);
+        assert!(legacy.is_set(State::CalibrateMag));
+        assert!(!legacy.is_set(State::GpsFixEver));
+        assert_eq!(vec!["GpsFixHome", "CalibrateMag"], legacy.as_names());
+    }
+
+    #[test]
+    fn adc_calibration() {
+        let ctx = context(
+            "Betaflight 3.0.1 (1234567) NAZE",
+            &[("vbatscale", "100"), ("currentMeter", "-50,300")],
+        );
+
+        let vbat = ElectricPotential::from_raw(2048, &ctx);
+        float_eq!(16.504, vbat.get::<si::electric_potential::volt>());
+
+        let amperage = ElectricCurrent::from_raw(124, &ctx);
+        float_eq!(4.9976, amperage.get::<si::electric_current::ampere>());
+    }
+
     #[test]
     fn electric_current() {
-        float_eq!(1.39, new_amps(139).get::<prelude::ampere>());
+        float_eq!(1.39, new_amps(139).get::<si::electric_current::ampere>());
     }
 
     #[test]
     fn electric_potential() {
-        float_eq!(16.32, new_vbat(1632).get::<prelude::volt>());
+        float_eq!(16.32, new_vbat(1632).get::<si::electric_potential::volt>());
+    }
+
+    #[test]
+    fn motor_output() {
+        let ctx = context(
+            "Betaflight 4.2.11 (948ba6339) STM32F7X2",
+            &[("motorOutput", "1000,2000")],
+        );
+        let percent = |raw| new_motor_output(raw, &ctx).get::<si::ratio::percent>();
+
+        float_eq!(0., percent(1000));
+        float_eq!(50., percent(1500));
+        float_eq!(100., percent(2000));
+    }
+
+    #[case(&[], 71.4286; "default poles")]
+    #[case(&[("motor_poles", "12")], 83.3333; "12 poles")]
+    fn erpm(headers: &[(&str, &str)], hz: f64) {
+        let ctx = context("Betaflight 4.3.0 (1234567) STM32F7X2", headers);
+        let frequency = new_erpm(300, &ctx);
+        float_eq!(hz, frequency.get::<si::frequency::hertz>());
+    }
+
+    #[test]
+    fn rssi() {
+        float_eq!(100., new_rssi(1023).get::<si::ratio::percent>());
+        float_eq!(0., new_rssi(0).get::<si::ratio::percent>());
+    }
+
+    fn rates(kind: Option<RatesType>, rc_rate: u8, expo: u8, rate: u8) -> Rates {
+        let axes = |value| Axes {
+            roll: value,
+            pitch: value,
+            yaw: value,
+        };
+
+        Rates {
+            kind,
+            rc_rates: Some(axes(rc_rate)),
+            rc_expo: Some(axes(expo)),
+            rates: Some(axes(rate)),
+            rate_limits: None,
+        }
+    }
+
+    #[case(Some(RatesType::Betaflight), 70, 0, 75, 500, 560.; "betaflight")]
+    #[case(None, 70, 0, 75, 500, 560.; "betaflight without rates type")]
+    #[case(Some(RatesType::Betaflight), 70, 0, 75, -500, -560.; "betaflight negative")]
+    #[case(Some(RatesType::Actual), 7, 54, 67, 500, 670.; "actual")]
+    #[case(Some(RatesType::Actual), 7, 54, 67, 250, 109.0625; "actual half stick")]
+    #[case(Some(RatesType::Quick), 100, 0, 67, 500, 670.; "quick")]
+    #[case(Some(RatesType::Betaflight), 255, 0, 90, 500, 1998.; "rate limit")]
+    fn rc_command(kind: Option<RatesType>, rc_rate: u8, expo: u8, rate: u8, raw: i32, dps: f64) {
+        let curve = RateCurve::new(&rates(kind, rc_rate, expo, rate), FirmwareKind::Betaflight);
+        float_eq!(dps, curve.unwrap().degrees_per_second(0, raw));
+    }
+
+    #[test]
+    fn rc_command_inav() {
+        let curve = RateCurve::new(&rates(None, 0, 0, 70), FirmwareKind::Inav).unwrap();
+        float_eq!(350., curve.degrees_per_second(2, 250));
+    }
+
+    #[test]
+    fn rc_command_unknown_rates_type() {
+        let rates = rates(Some(RatesType::Unknown(9)), 70, 0, 75);
+        assert_eq!(None, RateCurve::new(&rates, FirmwareKind::Betaflight));
     }
 
     mod resolution {
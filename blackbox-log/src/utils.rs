@@ -65,3 +65,29 @@ fn from(from: $name) -> u8 {
         }
     }
 }
+
+/// Returns `tests/logs/gps.bbl` with its firmware revision replaced and
+/// `headers` inserted right after it.
+#[cfg(test)]
+pub(crate) fn log_with_headers(revision: &str, headers: &[(&str, &str)]) -> alloc::vec::Vec<u8> {
+    use alloc::format;
+
+    const HEADER: &[u8] = b"H Firmware revision:";
+
+    let log = include_bytes!("../tests/logs/gps.bbl");
+    let start = log
+        .windows(HEADER.len())
+        .position(|window| window == HEADER)
+        .unwrap()
+        + HEADER.len();
+    let end = start + log[start..].iter().position(|&b| b == b'\n').unwrap();
+
+    let mut replacement = alloc::string::String::from(revision);
+    for (header, value) in headers {
+        replacement.push_str(&format!("\nH {header}:{value}"));
+    }
+
+    let mut log = log.to_vec();
+    log.splice(start..end, replacement.bytes());
+    log
+}
@@ -0,0 +1,641 @@
+//! Encoding of blackbox logs.
+//!
+//! [`Writer`] is the inverse of [`DataParser`](crate::DataParser): it encodes
+//! frames using the field definitions, predictors and encodings from a set of
+//! [`Headers`]. This can be used to re-encode a trimmed log, or to build
+//! synthetic logs from scratch by parsing a hand-written header section.
+//!
+//! # Example
+//!
+//! ```
+//! use blackbox_log::data::ParseEvent;
+//! use blackbox_log::writer::Writer;
+//! use blackbox_log::{DataParser, File, Headers};
+//!
+//! # let data = include_bytes!("../tests/logs/error-recovery.bbl");
+//! let file = File::new(data);
+//! let mut reader = file.get_reader(0);
+//! let headers = Headers::parse(&mut reader)?;
+//!
+//! let mut writer = Writer::new(&headers);
+//! let mut parser = DataParser::new(reader, &headers);
+//! let mut main_frames = 0;
+//! while let Some(event) = parser.next() {
+//!     // Keep only the first 100 main frames
+//!     if let ParseEvent::Main(_) = event {
+//!         main_frames += 1;
+//!         if main_frames > 100 {
+//!             break;
+//!         }
+//!     }
+//!
+//!     writer.write(&event)?;
+//! }
+//!
+//! let trimmed: Vec<u8> = writer.into_bytes();
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+
+use crate::data::{MainFrameHistory, ParseEvent};
+use crate::event::Event;
+use crate::frame::gps::RawGpsFrame;
+use crate::frame::main::RawMainFrame;
+use crate::frame::slow::RawSlowFrame;
+use crate::frame::{DataFrameKind, FrameKind, GpsHomeFrame, GpsPosition};
+use crate::Headers;
+
+pub type WriteResult<T> = Result<T, WriteError>;
+
+/// An error encountered while encoding a frame. The frame is not written.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum WriteError {
+    /// The log does not have a definition for this kind of frame.
+    MissingDefinition(FrameKind),
+    /// The number of values does not match the frame definition.
+    FieldCount {
+        frame: FrameKind,
+        expected: usize,
+        found: usize,
+    },
+    /// The value of `field` cannot be represented using its predictor and
+    /// encoding.
+    Unencodable { frame: FrameKind, field: String },
+    /// An inter frame was written after a [`Resume`](Event::Resume) event but
+    /// before any intra frame.
+    MissingIntra,
+}
+
+impl fmt::Display for WriteError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingDefinition(frame) => {
+                write!(f, "no definition for `{}` frames", char::from(*frame))
+            }
+            Self::FieldCount {
+                frame,
+                expected,
+                found,
+            } => write!(
+                f,
+                "expected {expected} values for `{}` frame, found {found}",
+                char::from(*frame)
+            ),
+            Self::Unencodable { frame, field } => write!(
+                f,
+                "cannot encode value of field `{field}` in `{}` frame",
+                char::from(*frame)
+            ),
+            Self::MissingIntra => write!(f, "inter frame written after resume before intra frame"),
+        }
+    }
+}
+
+// TODO: waiting on https://github.com/rust-lang/rust-clippy/pull/9545 to land
+#[allow(clippy::std_instead_of_core)]
+#[cfg(feature = "std")]
+impl std::error::Error for WriteError {}
+
+/// Encodes a log using the definitions from its [`Headers`].
+///
+/// The header section is copied as is from the log `headers` was parsed from.
+///
+/// Raw field values are passed in the order of the frame definition, ignoring
+/// any filter, with signed values stored as their two's complement (ie, `x as
+/// u32`).
+#[derive(Debug)]
+pub struct Writer<'data, 'headers> {
+    headers: &'headers Headers<'data>,
+    data: Vec<u8>,
+    main_frames: MainFrameHistory,
+    gps_home_frame: Option<GpsHomeFrame>,
+    awaiting_intra: bool,
+}
+
+impl<'data, 'headers> Writer<'data, 'headers> {
+    /// Creates a new `Writer` and writes the header section.
+    pub fn new(headers: &'headers Headers<'data>) -> Self {
+        Self {
+            headers,
+            data: headers.raw.to_vec(),
+            main_frames: MainFrameHistory::default(),
+            gps_home_frame: None,
+            awaiting_intra: false,
+        }
+    }
+
+    /// Re-encodes a [`ParseEvent`] from a [`DataParser`](crate::DataParser)
+    /// using the same [`Headers`].
+    ///
+    /// GPS home frames are not returned by the parser, so a GPS home frame is
+    /// written before any GPS frame whose
+    /// [`home`](crate::frame::GpsFrame::home) differs from the last one
+    /// written.
+    pub fn write(&mut self, event: &ParseEvent) -> WriteResult<()> {
+        match event {
+            ParseEvent::Event(event) => self.write_event(&event.event),
+            ParseEvent::Main(main) => self.write_main(main.raw.clone()),
+            ParseEvent::Slow(slow) => self.write_slow(&slow.raw.0),
+            ParseEvent::Gps(gps) => {
+                if let Some(home) = gps.home() {
+                    if self.gps_home_frame.as_ref().map(|frame| frame.0) != Some(home) {
+                        self.write_gps_home(home.latitude, home.longitude)?;
+                    }
+                }
+
+                self.write_gps(gps.raw.time, &gps.raw.values)
+            }
+        }
+    }
+
+    /// Writes an event frame.
+    ///
+    /// After a [`Resume`](Event::Resume) event, the next main frame must be an
+    /// intra frame.
+    pub fn write_event(&mut self, event: &Event) -> WriteResult<()> {
+        let mut out = frame_start(FrameKind::Event);
+        event.encode(
+            &mut out,
+            self.headers.firmware_kind,
+            self.headers.firmware_version.as_ref(),
+            self.main_frames.last_time(),
+        )?;
+
+        self.data.append(&mut out);
+        if let Event::Resume { time, .. } = *event {
+            self.main_frames = MainFrameHistory::starting_at(Some(time));
+            self.awaiting_intra = true;
+        }
+        Ok(())
+    }
+
+    /// Writes an intra (`I`) main frame.
+    pub fn write_intra(&mut self, iteration: u32, time: u64, values: &[u32]) -> WriteResult<()> {
+        self.write_main(RawMainFrame::new(true, iteration, time, values.to_vec()))
+    }
+
+    /// Writes an inter (`P`) main frame.
+    ///
+    /// `iteration` must be the next logged iteration after the previous main
+    /// frame, since it is not stored in inter frames.
+    pub fn write_inter(&mut self, iteration: u32, time: u64, values: &[u32]) -> WriteResult<()> {
+        self.write_main(RawMainFrame::new(false, iteration, time, values.to_vec()))
+    }
+
+    fn write_main(&mut self, frame: RawMainFrame) -> WriteResult<()> {
+        if self.awaiting_intra && !frame.intra {
+            return Err(WriteError::MissingIntra);
+        }
+
+        let kind = FrameKind::Data(if frame.intra {
+            DataFrameKind::Intra
+        } else {
+            DataFrameKind::Inter
+        });
+
+        let mut out = frame_start(kind);
+        frame.encode(&mut out, self.headers, &self.main_frames)?;
+
+        self.data.append(&mut out);
+        self.awaiting_intra = false;
+        self.main_frames.push(frame);
+        Ok(())
+    }
+
+    /// Writes a slow frame.
+    pub fn write_slow(&mut self, values: &[u32]) -> WriteResult<()> {
+        let mut out = frame_start(FrameKind::Data(DataFrameKind::Slow));
+        self.headers.slow_frame_def.encode(
+            &mut out,
+            self.headers,
+            &RawSlowFrame(values.to_vec()),
+        )?;
+
+        self.data.append(&mut out);
+        Ok(())
+    }
+
+    /// Writes a GPS frame.
+    pub fn write_gps(&mut self, time: u64, values: &[u32]) -> WriteResult<()> {
+        let kind = FrameKind::Data(DataFrameKind::Gps);
+        let def =
+            (self.headers.gps_frame_def.as_ref()).ok_or(WriteError::MissingDefinition(kind))?;
+
+        let mut out = frame_start(kind);
+        def.encode(
+            &mut out,
+            self.headers,
+            &self.main_frames,
+            self.gps_home_frame.as_ref(),
+            &RawGpsFrame {
+                time,
+                values: values.to_vec(),
+                home: self.gps_home_frame.as_ref().map(|home| home.0),
+            },
+        )?;
+
+        self.data.append(&mut out);
+        Ok(())
+    }
+
+    /// Writes a GPS home frame, which is used to predict the coordinates in
+    /// any following GPS frames.
+    pub fn write_gps_home(&mut self, latitude: i32, longitude: i32) -> WriteResult<()> {
+        let kind = FrameKind::Data(DataFrameKind::GpsHome);
+        let def = (self.headers.gps_home_frame_def.as_ref())
+            .ok_or(WriteError::MissingDefinition(kind))?;
+
+        let position = GpsPosition {
+            latitude,
+            longitude,
+        };
+
+        let mut out = frame_start(kind);
+        def.encode(&mut out, self.headers, position)?;
+
+        self.data.append(&mut out);
+        self.gps_home_frame = Some(GpsHomeFrame(position));
+        Ok(())
+    }
+
+    /// Returns the log encoded so far.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Returns the encoded log.
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.data
+    }
+}
+
+fn frame_start(kind: FrameKind) -> Vec<u8> {
+    alloc::vec![u8::from(kind)]
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+
+    use proptest::prelude::*;
+    use test_case::case;
+
+    use super::*;
+    use crate::event::{AdjustedValue, AdjustmentFunction};
+    use crate::headers::FirmwareKind;
+    use crate::units::{DisarmReason, FlagLayout, FlightModeSet};
+    use crate::utils::as_u32;
+    use crate::{DataParser, File};
+
+    const HEADERS: &str = "H Product:Blackbox flight data recorder by Nicholas Sherlock
+H Data version:2
+H Field I name:loopIteration,time,axisP[0],axisP[1],axisP[2],axisI[0],axisI[1],axisI[2],axisI[3],\
+                           rcCommand[3],vbatLatest,motor[0],motor[1],debug[0],debug[1],debug[2]
+H Field I signed:0,0,1,1,1,1,1,1,1,0,0,0,0,1,1,1
+H Field I predictor:0,0,0,0,0,0,0,0,0,4,9,11,5,0,8,0
+H Field I encoding:1,1,0,0,0,6,6,6,6,1,3,1,0,0,0,0
+H Field P predictor:6,2,1,1,1,3,3,3,3,1,1,1,1,1,2,2
+H Field P encoding:9,0,8,8,8,8,7,7,7,10,10,10,5,5,4,0
+H Field S name:flightModeFlags,stateFlags,failsafePhase,rxSignalReceived,rxFlightChannelsValid
+H Field S signed:0,0,0,0,0
+H Field S predictor:0,0,0,0,0
+H Field S encoding:1,1,7,7,7
+H Field H name:GPS_home[0],GPS_home[1]
+H Field H signed:1,1
+H Field H predictor:0,0
+H Field H encoding:0,0
+H Field G name:time,GPS_numSat,GPS_coord[0],GPS_coord[1],GPS_altitude,GPS_speed,GPS_ground_course
+H Field G signed:0,0,1,1,0,0,0
+H Field G predictor:10,0,7,7,0,0,0
+H Field G encoding:1,1,0,0,1,1,1
+H Firmware type:Cleanflight
+H Firmware revision:Betaflight 4.2.11 (948ba6339) STM32F7X2
+H minthrottle:1070
+H vbatref:420
+H motorOutput:48,2047
+";
+
+    #[derive(Debug, Clone, PartialEq)]
+    enum Decoded {
+        Event(Event),
+        Main {
+            iteration: u32,
+            time: u64,
+            values: Vec<u32>,
+        },
+        Slow(Vec<u32>),
+        Gps {
+            time: u64,
+            values: Vec<u32>,
+            home: Option<GpsPosition>,
+        },
+    }
+
+    #[derive(Debug, Clone)]
+    enum Op {
+        Main {
+            intra: bool,
+            delta: u32,
+            values: Vec<i32>,
+        },
+        Slow(Vec<u32>),
+        Gps {
+            delta: u32,
+            values: Vec<i32>,
+        },
+        GpsHome(i32, i32),
+        Event(Event),
+    }
+
+    fn decode(log: &[u8]) -> Vec<Decoded> {
+        let file = File::new(log);
+        let mut reader = file.get_reader(0);
+        let headers = Headers::parse(&mut reader).unwrap();
+
+        let mut parser = DataParser::new(reader, &headers);
+        let mut decoded = Vec::new();
+        while let Some(event) = parser.next() {
+            decoded.push(match event {
+                ParseEvent::Event(event) => Decoded::Event(event.event),
+                ParseEvent::Main(main) => Decoded::Main {
+                    iteration: main.raw.iteration,
+                    time: main.raw.time,
+                    values: main.raw.values.clone(),
+                },
+                ParseEvent::Slow(slow) => Decoded::Slow(slow.raw.0),
+                ParseEvent::Gps(gps) => Decoded::Gps {
+                    time: gps.raw.time,
+                    home: gps.home(),
+                    values: gps.raw.values,
+                },
+            });
+        }
+
+        decoded
+    }
+
+    /// Event times are offsets from the previous main frame, since they must
+    /// be close enough to expand from 32 bits
+    fn event() -> impl Strategy<Value = Event> {
+        let adjusted = prop_oneof![
+            (-1e6_f32..1e6).prop_map(AdjustedValue::Float),
+            any::<i32>().prop_map(AdjustedValue::Int),
+        ];
+        let time = || 0..0x8000_0000_u64;
+
+        prop_oneof![
+            time().prop_map(Event::SyncBeep),
+            (any::<(u8, bool, u8, u8, u8)>(), 0..0x80_u8).prop_map(
+                |((phase, rising, p, i, d), cycle)| Event::AutotuneCycleStart {
+                    phase,
+                    cycle,
+                    rising,
+                    p,
+                    i,
+                    d
+                }
+            ),
+            any::<(bool, bool, u8, u8, u8)>().prop_map(|(overshot, timed_out, p, i, d)| {
+                Event::AutotuneCycleResult {
+                    overshot,
+                    timed_out,
+                    p,
+                    i,
+                    d,
+                }
+            }),
+            any::<(i16, i8, i8, i16, i16)>().prop_map(
+                |(current, target, at_peak, first, second)| {
+                    Event::AutotuneTargets {
+                        current_angle: current,
+                        target_angle: target,
+                        target_angle_at_peak: at_peak,
+                        first_peak_angle: first,
+                        second_peak_angle: second,
+                    }
+                }
+            ),
+            any::<(u8, i32, i16)>().prop_map(|(axis, gyro_average, new_p)| Event::GtuneResult {
+                axis,
+                gyro_average,
+                new_p
+            }),
+            (1..0x80_u8, adjusted).prop_map(|(function, new_value)| {
+                Event::InflightAdjustment {
+                    function: AdjustmentFunction::new(function, FirmwareKind::Betaflight, None),
+                    new_value,
+                }
+            }),
+            (any::<u32>(), time()).prop_map(|(log_iteration, time)| Event::Resume {
+                log_iteration,
+                time
+            }),
+            any::<u32>()
+                .prop_map(|raw| Event::Disarm(DisarmReason::new(raw, FirmwareKind::Betaflight))),
+            any::<(u32, u32)>().prop_map(|(flags, last_flags)| Event::FlightMode {
+                flags: FlightModeSet::new(flags, FlagLayout::Betaflight),
+                last_flags: FlightModeSet::new(last_flags, FlagLayout::Betaflight),
+            }),
+            any::<u32>().prop_map(|error| Event::ImuFailure { error }),
+        ]
+    }
+
+    fn op() -> impl Strategy<Value = Op> {
+        let values = |len| prop::collection::vec(-0x4000..0x4000, len);
+
+        prop_oneof![
+            // vbatLatest is written as the negated difference to vbatref in 14 bits
+            8 => (any::<bool>(), 0..10_000_u32, values(8), 0..0x2000, values(5)).prop_map(
+                |(intra, delta, mut values, vbat, rest)| {
+                    values.push(vbat);
+                    values.extend(rest);
+                    Op::Main { intra, delta, values }
+                }
+            ),
+            1 => prop::collection::vec(0..8_u32, 5).prop_map(Op::Slow),
+            1 => (0..1000_u32, values(6)).prop_map(|(delta, values)| Op::Gps { delta, values }),
+            1 => any::<(i32, i32)>().prop_map(|(lat, lon)| Op::GpsHome(lat, lon)),
+            1 => event().prop_map(Op::Event),
+        ]
+    }
+
+    proptest! {
+        #[test]
+        fn round_trip(ops in prop::collection::vec(op(), 0..100)) {
+            let file = File::new(HEADERS.as_bytes());
+            let headers = Headers::parse(&mut file.get_reader(0)).unwrap();
+            let mut writer = Writer::new(&headers);
+
+            let mut expected = Vec::new();
+            let mut iteration = 0;
+            let mut time = 0;
+            // Inter frames need a previous intra frame to predict from
+            let mut awaiting_intra = true;
+            let mut home = None;
+
+            for op in ops {
+                match op {
+                    Op::Main { intra, delta, values } => {
+                        iteration += 1;
+                        time += u64::from(delta);
+                        let values = values.into_iter().map(as_u32).collect::<Vec<_>>();
+
+                        if intra || awaiting_intra {
+                            writer.write_intra(iteration, time, &values).unwrap();
+                        } else {
+                            writer.write_inter(iteration, time, &values).unwrap();
+                        }
+
+                        awaiting_intra = false;
+                        expected.push(Decoded::Main {
+                            iteration,
+                            time,
+                            values,
+                        });
+                    }
+                    Op::Slow(values) => {
+                        writer.write_slow(&values).unwrap();
+                        expected.push(Decoded::Slow(values));
+                    }
+                    Op::Gps { delta, values } => {
+                        let time = time + u64::from(delta);
+                        let values = values.into_iter().map(as_u32).collect::<Vec<_>>();
+
+                        writer.write_gps(time, &values).unwrap();
+                        expected.push(Decoded::Gps { time, values, home });
+                    }
+                    Op::GpsHome(latitude, longitude) => {
+                        writer.write_gps_home(latitude, longitude).unwrap();
+                        home = Some(GpsPosition { latitude, longitude });
+                    }
+                    Op::Event(mut event) => {
+                        if let Event::SyncBeep(offset) | Event::Resume { time: offset, .. } =
+                            &mut event
+                        {
+                            *offset += time;
+                        }
+
+                        writer.write_event(&event).unwrap();
+                        if let Event::Resume { time: resumed, .. } = event {
+                            time = resumed;
+                            awaiting_intra = true;
+                        }
+                        expected.push(Decoded::Event(event));
+                    }
+                }
+            }
+
+            let end = Event::End { disarm_reason: Some(DisarmReason::Switch) };
+            writer.write_event(&end).unwrap();
+            expected.push(Decoded::Event(end));
+
+            prop_assert_eq!(expected, decode(writer.as_bytes()));
+        }
+    }
+
+    #[test]
+    fn inter_after_resume() {
+        let file = File::new(HEADERS.as_bytes());
+        let headers = Headers::parse(&mut file.get_reader(0)).unwrap();
+        let mut writer = Writer::new(&headers);
+        let values = [0; 14];
+
+        writer.write_intra(0, 0, &values).unwrap();
+        writer
+            .write_event(&Event::Resume {
+                log_iteration: 10,
+                time: 100,
+            })
+            .unwrap();
+
+        assert_eq!(
+            Err(WriteError::MissingIntra),
+            writer.write_inter(1, 100, &values)
+        );
+        writer.write_intra(10, 100, &values).unwrap();
+        writer.write_inter(11, 110, &values).unwrap();
+    }
+
+    #[case(include_bytes!("../tests/logs/error-recovery.bbl"), false; "error recovery")]
+    #[case(include_bytes!("../tests/logs/gps.bbl"), true; "gps")]
+    fn reencode(log: &[u8], has_gps_home: bool) {
+        let file = File::new(log);
+        let mut reader = file.get_reader(0);
+        let headers = Headers::parse(&mut reader).unwrap();
+
+        let mut writer = Writer::new(&headers);
+        let mut parser = DataParser::new(reader, &headers);
+        while let Some(event) = parser.next() {
+            writer.write(&event).unwrap();
+        }
+
+        let original = decode(log);
+        assert!(!original.is_empty());
+        assert_eq!(original, decode(writer.as_bytes()));
+
+        let gps_home = original
+            .iter()
+            .any(|frame| matches!(frame, Decoded::Gps { home: Some(_), .. }));
+        assert_eq!(has_gps_home, gps_home);
+    }
+
+    #[test]
+    fn field_count() {
+        let file = File::new(HEADERS.as_bytes());
+        let headers = Headers::parse(&mut file.get_reader(0)).unwrap();
+        let mut writer = Writer::new(&headers);
+
+        let error = writer.write_slow(&[0; 3]).unwrap_err();
+        assert_eq!(
+            WriteError::FieldCount {
+                frame: FrameKind::Data(DataFrameKind::Slow),
+                expected: 5,
+                found: 3
+            },
+            error
+        );
+        assert_eq!(HEADERS.as_bytes(), writer.as_bytes());
+    }
+
+    #[test]
+    fn unencodable_iteration() {
+        let file = File::new(HEADERS.as_bytes());
+        let headers = Headers::parse(&mut file.get_reader(0)).unwrap();
+        let mut writer = Writer::new(&headers);
+
+        writer.write_intra(0, 0, &[0; 14]).unwrap();
+        let error = writer.write_inter(5, 0, &[0; 14]).unwrap_err();
+        assert_eq!(
+            WriteError::Unencodable {
+                frame: FrameKind::Data(DataFrameKind::Inter),
+                field: "loopIteration".into()
+            },
+            error
+        );
+        assert!(writer.write_inter(1, 0, &[0; 14]).is_ok());
+    }
+
+    #[test]
+    fn unencodable_time() {
+        let file = File::new(HEADERS.as_bytes());
+        let headers = Headers::parse(&mut file.get_reader(0)).unwrap();
+        let mut writer = Writer::new(&headers);
+
+        writer.write_intra(0, 0, &[0; 14]).unwrap();
+        // Too far from the last frame to be expanded from 32 bits
+        let error = writer.write_intra(32, 1 << 31, &[0; 14]).unwrap_err();
+        assert_eq!(
+            WriteError::Unencodable {
+                frame: FrameKind::Data(DataFrameKind::Intra),
+                field: "time".into()
+            },
+            error
+        );
+        assert!(writer.write_event(&Event::SyncBeep(1 << 32)).is_err());
+        assert!(writer.write_intra(32, (1 << 31) - 1, &[0; 14]).is_ok());
+    }
+}
@@ -75,7 +75,7 @@ fn new(headers: &Headers<'data>, mut data: DataParser<'data, '_>) -> Self {
 
         while let Some(frame) = data.next() {
             match frame {
-                ParseEvent::Event(event) => events.push(event),
+                ParseEvent::Event(event) => events.push(event.event),
                 ParseEvent::Main(frame) => main.update(frame),
                 ParseEvent::Slow(frame) => slow.update(frame),
                 ParseEvent::Gps(frame) => gps.update(frame),
@@ -152,6 +152,8 @@ fn new(name: String, unit: Unit) -> Self {
                 | Unit::Voltage
                 | Unit::Acceleration
                 | Unit::Rotation
+                | Unit::Frequency
+                | Unit::Percent
                 | Unit::GpsCoordinate
                 | Unit::Altitude
                 | Unit::Velocity
@@ -178,6 +180,8 @@ fn update(&mut self, value: Value) {
                 Value::Rotation(r) => {
                     r.get::<si::angular_velocity::degree_per_second>().round() as i128
                 }
+                Value::Frequency(f) => f.get::<si::frequency::hertz>().round() as i128,
+                Value::Percent(p) => (p.get::<si::ratio::percent>() * 10.).round() as i128,
                 Value::GpsCoordinate(c) => (c * 10000000.).round() as i128,
                 Value::Altitude(a) => a.get::<si::length::meter>().round() as i128,
                 Value::Velocity(v) => {